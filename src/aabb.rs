@@ -0,0 +1,111 @@
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+
+/// Axis-aligned bounding box.
+#[derive(Clone, Copy)]
+pub struct Aabb
+{
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb
+{
+    pub const EMPTY: Aabb = Aabb {
+        min: Vec3 { e: [f32::INFINITY; 3] },
+        max: Vec3 { e: [f32::NEG_INFINITY; 3] },
+    };
+
+    /// Box containing all of space, used by unbounded objects such as planes.
+    pub const UNIVERSE: Aabb = Aabb {
+        min: Vec3 { e: [f32::NEG_INFINITY; 3] },
+        max: Vec3 { e: [f32::INFINITY; 3] },
+    };
+
+    pub fn new(a: Vec3, b: Vec3) -> Aabb
+    {
+        Aabb {
+            min: Vec3::new(a.x().min(b.x()), a.y().min(b.y()), a.z().min(b.z())),
+            max: Vec3::new(a.x().max(b.x()), a.y().max(b.y()), a.z().max(b.z())),
+        }.pad()
+    }
+
+    pub fn surrounding(a: &Aabb, b: &Aabb) -> Aabb
+    {
+        Aabb {
+            min: Vec3::new(a.min.x().min(b.min.x()), a.min.y().min(b.min.y()), a.min.z().min(b.min.z())),
+            max: Vec3::new(a.max.x().max(b.max.x()), a.max.y().max(b.max.y()), a.max.z().max(b.max.z())),
+        }
+    }
+
+    // Flat primitives such as quads produce a box with zero thickness along one axis,
+    // which the slab test would miss, so every axis is given a minimum extent.
+    fn pad(mut self) -> Aabb
+    {
+        let delta = 0.0001;
+        for axis in 0..3 {
+            if self.max.e[axis] - self.min.e[axis] < delta
+            {
+                self.min.e[axis] -= delta / 2.0;
+                self.max.e[axis] += delta / 2.0;
+            }
+        }
+        self
+    }
+
+    /// Whether the box extends infinitely along some axis.
+    pub fn is_unbounded(&self) -> bool
+    {
+        (0..3).any(|axis| self.min.e[axis] == f32::NEG_INFINITY || self.max.e[axis] == f32::INFINITY)
+    }
+
+    pub fn centroid(&self) -> Vec3
+    {
+        0.5 * (self.min + self.max)
+    }
+
+    pub fn surface_area(&self) -> f32
+    {
+        let extent = self.max - self.min;
+        2.0 * (extent.x() * extent.y() + extent.y() * extent.z() + extent.z() * extent.x())
+    }
+
+    pub fn longest_axis(&self) -> usize
+    {
+        let extent = self.max - self.min;
+        if extent.x() > extent.y() && extent.x() > extent.z() { 0 } else if extent.y() > extent.z() { 1 } else { 2 }
+    }
+
+    /// Part of `t_min..t_max` during which the ray is inside the box, if any.
+    pub fn interval(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<(f32, f32)>
+    {
+        let mut t_min = t_min;
+        let mut t_max = t_max;
+
+        for axis in 0..3 {
+            let inv_d = 1.0 / ray.direction.e[axis];
+            let mut t0 = (self.min.e[axis] - ray.origin.e[axis]) * inv_d;
+            let mut t1 = (self.max.e[axis] - ray.origin.e[axis]) * inv_d;
+
+            if inv_d < 0.0
+            {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+
+            t_min = if t0 > t_min { t0 } else { t_min };
+            t_max = if t1 < t_max { t1 } else { t_max };
+
+            if t_max <= t_min
+            {
+                return None;
+            }
+        }
+
+        Some((t_min, t_max))
+    }
+
+    pub fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> bool
+    {
+        self.interval(ray, t_min, t_max).is_some()
+    }
+}
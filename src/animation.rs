@@ -0,0 +1,166 @@
+use crate::vec3::{Mat3, Mat4, Quat, Vec3};
+
+// Largest cosine between two axes of a keyframe matrix still taken as perpendicular.
+const SHEAR_TOLERANCE: f32 = 1e-4;
+
+/// Placement of an animated object at one point in time, split into parts that can be
+/// interpolated separately: the object is scaled, then rotated, then moved.
+#[derive(Clone, Copy, Debug)]
+pub struct Keyframe
+{
+    pub time: f32,
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+}
+
+impl Keyframe
+{
+    pub fn new(time: f32, translation: Vec3, rotation: Quat, scale: Vec3) -> Keyframe
+    {
+        Keyframe { time, translation, rotation, scale }
+    }
+
+    /// Splits an affine transform into its parts. Returns `None` if it cannot be inverted or
+    /// shears the object, as a rotation followed by a non-uniform scale does, since the parts
+    /// cannot represent that.
+    pub fn from_matrix(time: f32, matrix: &Mat4) -> Option<Keyframe>
+    {
+        let linear = matrix.linear();
+        let mut columns = [linear.column(0), linear.column(1), linear.column(2)];
+        let mut scale = Vec3::new(columns[0].length(), columns[1].length(), columns[2].length());
+
+        // A mirror image shows up as a negative determinant, which a rotation cannot express.
+        if linear.determinant() < 0.0
+        {
+            scale.e[0] = -scale.e[0];
+        }
+        for (axis, column) in columns.iter_mut().enumerate() {
+            *column = *column / scale.e[axis];
+        }
+
+        // Without shear the scaled axes stay perpendicular. A zero scale leaves NaNs, which fail
+        // the comparison as well.
+        let perpendicular = |a: Vec3, b: Vec3| a.dot(b).abs() < SHEAR_TOLERANCE;
+        if !(perpendicular(columns[0], columns[1]) && perpendicular(columns[0], columns[2]) && perpendicular(columns[1], columns[2]))
+        {
+            return None;
+        }
+
+        let rotation = Quat::from_mat3(&Mat3::from_columns(columns[0], columns[1], columns[2]));
+        Some(Keyframe { time, translation: matrix.translation_part(), rotation, scale })
+    }
+
+    pub fn matrix(&self) -> Mat4
+    {
+        Mat4::from_linear(self.rotation.to_mat3() * Mat3::from_diagonal(self.scale), self.translation)
+    }
+
+    /// Inverse of [`matrix`](Keyframe::matrix), built from the parts without inverting a matrix.
+    /// Returns `None` if the scale is zero along some axis.
+    pub fn inverse_matrix(&self) -> Option<Mat4>
+    {
+        if self.scale.e.contains(&0.0)
+        {
+            return None;
+        }
+        let inverse_scale = Vec3::new(1.0 / self.scale.x(), 1.0 / self.scale.y(), 1.0 / self.scale.z());
+        let linear = Mat3::from_diagonal(inverse_scale) * self.rotation.to_mat3().transpose();
+        Some(Mat4::from_linear(linear, -(linear * self.translation)))
+    }
+
+    // Translation and scale are interpolated linearly and the rotation along the shortest arc.
+    fn interpolate(&self, other: &Keyframe, time: f32) -> Keyframe
+    {
+        let t = (time - self.time) / (other.time - self.time);
+        Keyframe {
+            time,
+            translation: (1.0 - t) * self.translation + t * other.translation,
+            rotation: self.rotation.slerp(other.rotation, t),
+            scale: (1.0 - t) * self.scale + t * other.scale,
+        }
+    }
+}
+
+/// Transform changing over time, interpolated between keyframes. Before the first and after the
+/// last keyframe it holds still.
+#[derive(Clone, Debug)]
+pub struct AnimatedTransform
+{
+    keyframes: Vec<Keyframe>,
+}
+
+impl AnimatedTransform
+{
+    /// Panics if there are no keyframes.
+    pub fn new(mut keyframes: Vec<Keyframe>) -> AnimatedTransform
+    {
+        assert!(!keyframes.is_empty(), "an animated transform needs at least one keyframe");
+        keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+        AnimatedTransform { keyframes }
+    }
+
+    pub fn keyframes(&self) -> &[Keyframe]
+    {
+        &self.keyframes
+    }
+
+    pub fn at(&self, time: f32) -> Keyframe
+    {
+        let next = self.keyframes.partition_point(|keyframe| keyframe.time <= time);
+        if next == 0
+        {
+            return self.keyframes[0];
+        }
+        if next == self.keyframes.len()
+        {
+            return self.keyframes[next - 1];
+        }
+        self.keyframes[next - 1].interpolate(&self.keyframes[next], time)
+    }
+
+    pub fn matrix(&self, time: f32) -> Mat4
+    {
+        self.at(time).matrix()
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn assert_same_matrix(a: &Mat4, b: &Mat4)
+    {
+        for (row_a, row_b) in a.m.iter().zip(&b.m) {
+            for (x, y) in row_a.iter().zip(row_b) {
+                assert!((x - y).abs() < 1e-4, "{a:?} != {b:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn from_matrix_splits_a_mirrored_transform()
+    {
+        let matrix = Mat4::translation(Vec3::new(1.0, 2.0, -3.0))
+            * Mat4::rotation(Vec3::new(1.0, 2.0, 0.5), 70.0)
+            * Mat4::scaling(Vec3::new(2.0, -0.5, 1.5));
+        let keyframe = Keyframe::from_matrix(0.0, &matrix).unwrap();
+
+        assert!(keyframe.scale.x() * keyframe.scale.y() * keyframe.scale.z() < 0.0);
+        assert_same_matrix(&keyframe.matrix(), &matrix);
+        assert_same_matrix(&(keyframe.matrix() * keyframe.inverse_matrix().unwrap()), &Mat4::identity());
+    }
+
+    #[test]
+    fn from_matrix_rejects_shear_and_singular_transforms()
+    {
+        let shear = Mat4::scaling(Vec3::new(3.0, 1.0, 1.0)) * Mat4::rotation_z(30.0);
+        assert!(Keyframe::from_matrix(0.0, &shear).is_none());
+        assert!(Keyframe::from_matrix(0.0, &Mat4::scaling(Vec3::new(1.0, 0.0, 1.0))).is_none());
+
+        // Scaling before rotating keeps the axes perpendicular.
+        let scale_then_rotate = Mat4::rotation_z(30.0) * Mat4::scaling(Vec3::new(3.0, 1.0, 1.0));
+        assert!(Keyframe::from_matrix(0.0, &scale_then_rotate).is_some());
+    }
+}
@@ -0,0 +1,78 @@
+use std::sync::Arc;
+
+use rand::Rng;
+
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::material::Material;
+use crate::quad::Quad;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+
+/// Axis-aligned box made of six quads sharing one material. Every face has its own `u`, `v`
+/// coordinates running from 0 to 1, oriented so textures appear upright and unmirrored from
+/// outside the box.
+pub struct BoxShape<T: Material>
+{
+    sides: [Quad<Arc<T>>; 6],
+    bbox: Aabb,
+}
+
+impl<T: Material> BoxShape<T>
+{
+    /// Box spanning two opposite corners `a` and `b`.
+    pub fn new(a: Vec3, b: Vec3, material: T) -> BoxShape<T>
+    {
+        let min = Vec3::new(a.x().min(b.x()), a.y().min(b.y()), a.z().min(b.z()));
+        let max = Vec3::new(a.x().max(b.x()), a.y().max(b.y()), a.z().max(b.z()));
+
+        let dx = Vec3::new(max.x() - min.x(), 0.0, 0.0);
+        let dy = Vec3::new(0.0, max.y() - min.y(), 0.0);
+        let dz = Vec3::new(0.0, 0.0, max.z() - min.z());
+
+        let material = Arc::new(material);
+        let side = |q: Vec3, u: Vec3, v: Vec3| Quad::new(q, u, v, material.clone());
+
+        let sides = [
+            side(Vec3::new(min.x(), min.y(), max.z()), dx, dy),
+            side(Vec3::new(max.x(), min.y(), max.z()), -dz, dy),
+            side(Vec3::new(max.x(), min.y(), min.z()), -dx, dy),
+            side(Vec3::new(min.x(), min.y(), min.z()), dz, dy),
+            side(Vec3::new(min.x(), max.y(), max.z()), dx, -dz),
+            side(Vec3::new(min.x(), min.y(), min.z()), dx, dz),
+        ];
+
+        BoxShape { sides, bbox: Aabb::new(min, max) }
+    }
+}
+
+impl<T: Material> Hittable for BoxShape<T> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        let mut closest = t_max;
+        let mut res = None;
+
+        for side in &self.sides {
+            if let Some(hit_record) = side.hit(ray, t_min, closest)
+            {
+                closest = hit_record.t;
+                res = Some(hit_record);
+            }
+        }
+
+        res
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    // Every side is sampled with the same probability, so the density is their average.
+    fn pdf(&self, origin: &Vec3, direction: &Vec3, time: f32) -> f32 {
+        self.sides.iter().map(|side| side.pdf(origin, direction, time)).sum::<f32>() / self.sides.len() as f32
+    }
+
+    fn sample(&self, origin: &Vec3, time: f32) -> Vec3 {
+        let index = rand::thread_rng().gen_range(0..self.sides.len());
+        self.sides[index].sample(origin, time)
+    }
+}
@@ -0,0 +1,514 @@
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable, HittableList};
+use crate::ray::Ray;
+
+/// Bounding volume hierarchy split at the median along the longest axis.
+pub struct BvhNode
+{
+    left: Box<dyn Hittable>,
+    right: Box<dyn Hittable>,
+    bbox: Aabb,
+}
+
+impl BvhNode
+{
+    pub fn new(list: HittableList) -> BvhNode
+    {
+        // Unbounded objects would stretch every box they end up in to infinity, so they are kept
+        // next to the hierarchy and tested for every ray.
+        let (unbounded, bounded): (Vec<_>, Vec<_>) = list.objects.into_iter().partition(|h| h.bounding_box().is_unbounded());
+        if unbounded.is_empty()
+        {
+            return BvhNode::from_objects(bounded);
+        }
+
+        BvhNode {
+            left: Box::new(BvhNode::from_objects(bounded)),
+            right: Box::new(HittableList::new(unbounded)),
+            bbox: Aabb::UNIVERSE,
+        }
+    }
+
+    fn from_objects(mut objects: Vec<Box<dyn Hittable>>) -> BvhNode
+    {
+        let bbox = objects.iter().fold(Aabb::EMPTY, |bbox, h| Aabb::surrounding(&bbox, &h.bounding_box()));
+
+        let (left, right): (Box<dyn Hittable>, Box<dyn Hittable>) = match objects.len() {
+            0 => (Box::new(HittableList::new(vec![])), Box::new(HittableList::new(vec![]))),
+            1 => (objects.pop().unwrap(), Box::new(HittableList::new(vec![]))),
+            2 => {
+                let right = objects.pop().unwrap();
+                (objects.pop().unwrap(), right)
+            }
+            _ => {
+                let axis = bbox.longest_axis();
+                objects.sort_by(|a, b| {
+                    let a = a.bounding_box().centroid().e[axis];
+                    let b = b.bounding_box().centroid().e[axis];
+                    a.total_cmp(&b)
+                });
+
+                let right = objects.split_off(objects.len() / 2);
+                (Box::new(BvhNode::from_objects(objects)), Box::new(BvhNode::from_objects(right)))
+            }
+        };
+
+        BvhNode { left, right, bbox }
+    }
+}
+
+impl Hittable for BvhNode
+{
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        if !self.bbox.hit(ray, t_min, t_max)
+        {
+            return None;
+        }
+
+        let hit_left = self.left.hit(ray, t_min, t_max);
+        let closest = hit_left.as_ref().map_or(t_max, |h| h.t);
+        let hit_right = self.right.hit(ray, t_min, closest);
+
+        hit_right.or(hit_left)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    fn transmittance(&self, ray: &Ray, t_min: f32, t_max: f32) -> f32 {
+        if !self.bbox.hit(ray, t_min, t_max)
+        {
+            return 1.0;
+        }
+
+        let left = self.left.transmittance(ray, t_min, t_max);
+        if left <= 0.0
+        {
+            return 0.0;
+        }
+        left * self.right.transmittance(ray, t_min, t_max)
+    }
+}
+
+const BIN_COUNT: usize = 12;
+const MAX_LEAF_SIZE: usize = 4;
+const MAX_SAH_DEPTH: usize = 32;
+const TRAVERSAL_COST: f32 = 1.0;
+
+#[derive(Clone, Copy)]
+struct FlatNode
+{
+    bbox: Aabb,
+    // For leaves this is the index of the first object, for interior nodes the index of the
+    // second child. The first child of an interior node always directly follows it.
+    offset: usize,
+    count: usize,
+    axis: usize,
+}
+
+struct BuildItem
+{
+    bbox: Aabb,
+    centroid: crate::vec3::Vec3,
+    index: usize,
+}
+
+#[derive(Clone, Copy, Default)]
+struct Bin
+{
+    bbox: Option<Aabb>,
+    count: usize,
+}
+
+/// Shape of a built [`FlatBvh`]. `leaf_size_histogram[n]` counts the leaves holding `n` objects.
+pub struct BvhStats
+{
+    pub node_count: usize,
+    pub unbounded_count: usize,
+    pub leaf_count: usize,
+    pub max_depth: usize,
+    pub leaf_size_histogram: Vec<usize>,
+}
+
+impl std::fmt::Display for BvhStats
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "BVH nodes: {}, leaves: {}, max depth: {}", self.node_count, self.leaf_count, self.max_depth)?;
+        write!(f, "Leaf sizes:")?;
+        for (size, count) in self.leaf_size_histogram.iter().enumerate().filter(|(_, &c)| c > 0) {
+            write!(f, " {size}x{count}")?;
+        }
+        if self.unbounded_count > 0
+        {
+            write!(f, "\nUnbounded objects: {}", self.unbounded_count)?;
+        }
+        Ok(())
+    }
+}
+
+/// Bounding volume hierarchy built with the surface area heuristic and stored as a flat array of
+/// nodes in depth-first order, traversed with an explicit stack. Unbounded objects such as planes
+/// are kept outside the hierarchy and tested for every ray.
+pub struct FlatBvh
+{
+    nodes: Vec<FlatNode>,
+    objects: Vec<Box<dyn Hittable>>,
+    unbounded: Vec<Box<dyn Hittable>>,
+    stats: BvhStats,
+}
+
+impl FlatBvh
+{
+    pub fn new(list: HittableList) -> FlatBvh
+    {
+        let (unbounded, bounded): (Vec<_>, Vec<_>) = list.objects.into_iter().partition(|h| h.bounding_box().is_unbounded());
+
+        let mut items: Vec<BuildItem> = bounded.iter().enumerate().map(|(index, h)| {
+            let bbox = h.bounding_box();
+            BuildItem { bbox, centroid: bbox.centroid(), index }
+        }).collect();
+
+        let len = items.len();
+        let mut builder = BvhBuilder {
+            items: &mut items,
+            nodes: Vec::with_capacity(2 * len),
+            stats: BvhStats { node_count: 0, unbounded_count: unbounded.len(), leaf_count: 0, max_depth: 0, leaf_size_histogram: vec![0; MAX_LEAF_SIZE + 1] },
+        };
+        if len > 0
+        {
+            builder.build(0, len, 0);
+        }
+        let (nodes, mut stats) = (builder.nodes, builder.stats);
+        stats.node_count = nodes.len();
+
+        let mut objects: Vec<Option<Box<dyn Hittable>>> = bounded.into_iter().map(Some).collect();
+        let objects = items.iter().map(|item| objects[item.index].take().unwrap()).collect();
+
+        FlatBvh { nodes, objects, unbounded, stats }
+    }
+
+    pub fn stats(&self) -> &BvhStats
+    {
+        &self.stats
+    }
+}
+
+// State of a build in progress: the items are reordered in place so every leaf covers a
+// contiguous range of them, while nodes are appended in depth-first order.
+struct BvhBuilder<'a>
+{
+    items: &'a mut [BuildItem],
+    nodes: Vec<FlatNode>,
+    stats: BvhStats,
+}
+
+impl BvhBuilder<'_>
+{
+    fn build(&mut self, start: usize, end: usize, depth: usize) -> usize
+    {
+        let node_index = self.nodes.len();
+        let bbox = self.items[start..end].iter().fold(Aabb::EMPTY, |bbox, item| Aabb::surrounding(&bbox, &item.bbox));
+        self.nodes.push(FlatNode { bbox, offset: start, count: end - start, axis: 0 });
+        self.stats.max_depth = self.stats.max_depth.max(depth);
+
+        let count = end - start;
+        if count <= 1
+        {
+            self.add_leaf(count);
+            return node_index;
+        }
+
+        let centroid_bounds = self.items[start..end].iter()
+            .fold(Aabb::EMPTY, |bbox, item| Aabb::surrounding(&bbox, &Aabb { min: item.centroid, max: item.centroid }));
+        let axis = centroid_bounds.longest_axis();
+        let axis_min = centroid_bounds.min.e[axis];
+        let axis_extent = centroid_bounds.max.e[axis] - axis_min;
+
+        // All centroids coincide, so no split can separate the objects.
+        if axis_extent <= 0.0
+        {
+            if count <= MAX_LEAF_SIZE
+            {
+                self.add_leaf(count);
+                return node_index;
+            }
+            return self.split(start, start + count / 2, end, node_index, axis, depth);
+        }
+
+        let bin_of = |item: &BuildItem| (((item.centroid.e[axis] - axis_min) / axis_extent * BIN_COUNT as f32) as usize).min(BIN_COUNT - 1);
+
+        let mid = if depth >= MAX_SAH_DEPTH
+        {
+            None
+        } else {
+            let mut bins = [Bin::default(); BIN_COUNT];
+            for item in &self.items[start..end] {
+                let bin = &mut bins[bin_of(item)];
+                bin.count += 1;
+                bin.bbox = Some(bin.bbox.map_or(item.bbox, |b| Aabb::surrounding(&b, &item.bbox)));
+            }
+
+            let mut best_cost = f32::INFINITY;
+            let mut best_split = 0;
+            for split in 1..BIN_COUNT {
+                let cost = TRAVERSAL_COST + (sah_cost(&bins[..split]) + sah_cost(&bins[split..])) / bbox.surface_area();
+                if cost < best_cost
+                {
+                    best_cost = cost;
+                    best_split = split;
+                }
+            }
+
+            if best_cost >= count as f32 && count <= MAX_LEAF_SIZE
+            {
+                self.add_leaf(count);
+                return node_index;
+            }
+
+            let mid = start + partition(&mut self.items[start..end], |item| bin_of(item) < best_split);
+            if mid == start || mid == end { None } else { Some(mid) }
+        };
+
+        let mid = mid.unwrap_or_else(|| {
+            self.items[start..end].sort_by(|a, b| a.centroid.e[axis].total_cmp(&b.centroid.e[axis]));
+            start + count / 2
+        });
+
+        self.split(start, mid, end, node_index, axis, depth)
+    }
+
+    fn split(&mut self, start: usize, mid: usize, end: usize, node_index: usize, axis: usize, depth: usize) -> usize
+    {
+        self.build(start, mid, depth + 1);
+        let second = self.build(mid, end, depth + 1);
+        self.nodes[node_index] = FlatNode { offset: second, count: 0, axis, ..self.nodes[node_index] };
+        node_index
+    }
+
+    fn add_leaf(&mut self, count: usize)
+    {
+        self.stats.leaf_count += 1;
+        if self.stats.leaf_size_histogram.len() <= count
+        {
+            self.stats.leaf_size_histogram.resize(count + 1, 0);
+        }
+        self.stats.leaf_size_histogram[count] += 1;
+    }
+}
+
+fn sah_cost(bins: &[Bin]) -> f32
+{
+    let (bbox, count) = bins.iter().fold((None, 0), |(bbox, count): (Option<Aabb>, usize), bin| {
+        let bbox = match (bbox, bin.bbox) {
+            (Some(a), Some(b)) => Some(Aabb::surrounding(&a, &b)),
+            (a, b) => a.or(b),
+        };
+        (bbox, count + bin.count)
+    });
+    bbox.map_or(0.0, |b| b.surface_area() * count as f32)
+}
+
+fn partition<T>(items: &mut [T], predicate: impl Fn(&T) -> bool) -> usize
+{
+    let mut first = 0;
+    for i in 0..items.len() {
+        if predicate(&items[i])
+        {
+            items.swap(first, i);
+            first += 1;
+        }
+    }
+    first
+}
+
+impl Hittable for FlatBvh
+{
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        let mut closest = t_max;
+        let mut res = None;
+
+        for object in &self.unbounded {
+            if let Some(hit_record) = object.hit(ray, t_min, closest)
+            {
+                closest = hit_record.t;
+                res = Some(hit_record);
+            }
+        }
+
+        if self.nodes.is_empty()
+        {
+            return res;
+        }
+
+        let mut stack = [0usize; 64];
+        let mut stack_len = 1;
+
+        while stack_len > 0 {
+            stack_len -= 1;
+            let node = &self.nodes[stack[stack_len]];
+
+            if !node.bbox.hit(ray, t_min, closest)
+            {
+                continue;
+            }
+
+            if node.count > 0
+            {
+                for object in &self.objects[node.offset..node.offset + node.count] {
+                    if let Some(hit_record) = object.hit(ray, t_min, closest)
+                    {
+                        closest = hit_record.t;
+                        res = Some(hit_record);
+                    }
+                }
+                continue;
+            }
+
+            // Visit the child closer to the ray origin first so that `closest` shrinks early.
+            let first = stack[stack_len] + 1;
+            let (near, far) = if ray.direction.e[node.axis] < 0.0 { (node.offset, first) } else { (first, node.offset) };
+            stack[stack_len] = far;
+            stack[stack_len + 1] = near;
+            stack_len += 2;
+        }
+
+        res
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        if !self.unbounded.is_empty()
+        {
+            return Aabb::UNIVERSE;
+        }
+        self.nodes.first().map_or(Aabb::EMPTY, |node| node.bbox)
+    }
+
+    // Unlike `hit` every object along the ray contributes, so the whole range is always searched.
+    fn transmittance(&self, ray: &Ray, t_min: f32, t_max: f32) -> f32 {
+        let mut transmittance = 1.0;
+        for object in &self.unbounded {
+            transmittance *= object.transmittance(ray, t_min, t_max);
+            if transmittance <= 0.0
+            {
+                return 0.0;
+            }
+        }
+
+        if self.nodes.is_empty()
+        {
+            return transmittance;
+        }
+
+        let mut stack = [0usize; 64];
+        let mut stack_len = 1;
+
+        while stack_len > 0 {
+            stack_len -= 1;
+            let index = stack[stack_len];
+            let node = &self.nodes[index];
+
+            if !node.bbox.hit(ray, t_min, t_max)
+            {
+                continue;
+            }
+
+            if node.count > 0
+            {
+                for object in &self.objects[node.offset..node.offset + node.count] {
+                    transmittance *= object.transmittance(ray, t_min, t_max);
+                    if transmittance <= 0.0
+                    {
+                        return 0.0;
+                    }
+                }
+                continue;
+            }
+
+            stack[stack_len] = node.offset;
+            stack[stack_len + 1] = index + 1;
+            stack_len += 2;
+        }
+
+        transmittance
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+    use crate::material::Metal;
+    use crate::plane::Plane;
+    use crate::quad::Quad;
+    use crate::sphere::Sphere;
+    use crate::vec3::Vec3;
+
+    const BOUNDED_COUNT: usize = 258;
+
+    fn random_vec3(rng: &mut StdRng, range: f32) -> Vec3
+    {
+        Vec3::new(rng.gen_range(-range..range), rng.gen_range(-range..range), rng.gen_range(-range..range))
+    }
+
+    // Spheres and quads of all sizes, a clump of spheres sharing one center that no split can
+    // separate, and a plane, which has to stay outside the hierarchy.
+    fn random_scene(rng: &mut StdRng) -> HittableList
+    {
+        let material = || Metal::new(Vec3::new(0.5, 0.5, 0.5), 0.0);
+        let mut list = HittableList::new(vec![]);
+        for _ in 0..200 {
+            list.objects.push(Box::new(Sphere::new(random_vec3(rng, 10.0), rng.gen_range(0.05..1.5), material())));
+        }
+        for _ in 0..50 {
+            list.objects.push(Box::new(Quad::new(random_vec3(rng, 10.0), random_vec3(rng, 2.0), random_vec3(rng, 2.0), material())));
+        }
+        for i in 0..8 {
+            list.objects.push(Box::new(Sphere::new(Vec3::new(3.0, 3.0, 3.0), 0.2 + 0.1 * i as f32, material())));
+        }
+        list.objects.push(Box::new(Plane::new(Vec3::new(0.0, -8.0, 0.0), Vec3::new(0.1, 1.0, 0.0), 1.0, material())));
+        list
+    }
+
+    #[test]
+    fn finds_the_same_nearest_hit_as_a_linear_list()
+    {
+        let mut rng = StdRng::seed_from_u64(7);
+        let list = random_scene(&mut rng);
+        let flat = FlatBvh::new(random_scene(&mut StdRng::seed_from_u64(7)));
+        let median = BvhNode::new(random_scene(&mut StdRng::seed_from_u64(7)));
+
+        let mut hits = 0;
+        for _ in 0..5000 {
+            let ray = Ray::new(random_vec3(&mut rng, 12.0), random_vec3(&mut rng, 1.0), 0.0);
+            let expected = list.hit(&ray, 0.001, f32::INFINITY).map(|h| h.t);
+            for actual in [flat.hit(&ray, 0.001, f32::INFINITY), median.hit(&ray, 0.001, f32::INFINITY)] {
+                match (expected, actual.map(|h| h.t)) {
+                    (None, None) => {}
+                    (Some(expected), Some(actual)) => assert!((expected - actual).abs() <= 1e-5 * expected.max(1.0), "{expected} != {actual}"),
+                    (expected, actual) => panic!("{expected:?} != {actual:?}"),
+                }
+            }
+            hits += expected.is_some() as usize;
+        }
+        assert!(hits > 1000, "only {hits} rays hit anything");
+    }
+
+    #[test]
+    fn stats_describe_the_built_tree()
+    {
+        let bvh = FlatBvh::new(random_scene(&mut StdRng::seed_from_u64(11)));
+        let stats = bvh.stats();
+
+        assert_eq!(stats.unbounded_count, 1);
+        assert_eq!(stats.node_count, bvh.nodes.len());
+        assert_eq!(stats.node_count, 2 * stats.leaf_count - 1);
+        assert_eq!(stats.leaf_size_histogram.len(), MAX_LEAF_SIZE + 1);
+        assert_eq!(stats.leaf_size_histogram.iter().sum::<usize>(), stats.leaf_count);
+        assert_eq!(stats.leaf_size_histogram.iter().enumerate().map(|(size, count)| size * count).sum::<usize>(), BOUNDED_COUNT);
+        assert!(stats.max_depth > 0 && stats.max_depth < 64);
+    }
+}
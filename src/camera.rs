@@ -1,24 +1,47 @@
 use rand::rngs::ThreadRng;
 use rand::{thread_rng, Rng};
 use rayon::prelude::*;
-use std::fs;
-use std::time::Instant;
 
-use crate::color::{write_color, Color};
-use crate::hittable::{Hittable, HittableList};
+use crate::color::Color;
+use crate::framebuffer::Framebuffer;
+use crate::hittable::{HitRecord, Hittable, HittableList};
 use crate::ray::Ray;
-use crate::vec3::Vec3;
+use crate::vec3::{Mat4, Vec3};
 
+/// How the shutter lets light through while it is open, which decides the time of every ray.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ShutterCurve {
+    /// Every moment between opening and closing contributes equally.
+    Box,
+    /// Exposure ramps up to a peak halfway through and back down, giving softer motion trails.
+    Triangle,
+    /// Scans the image from top to bottom like a CMOS sensor. Each row is exposed for a fraction
+    /// `exposure` of the shutter interval, above 0 and at most 1, with the first row starting at
+    /// opening and the last row ending at closing, so fast motion comes out skewed.
+    Rolling { exposure: f32 },
+}
+
+/// Perspective camera with optional depth of field that renders a world into a [`Framebuffer`].
+#[derive(Clone)]
 pub struct Camera {
-    pub aspect_ratio: f32,
     pub image_width: i32,
+    /// Rays traced through every pixel, 100 by default.
     pub samples_per_pixel: i32,
+    /// Longest path followed from the camera, 50 bounces by default.
     pub max_depth: i32,
+    /// Number of bounces every path takes before Russian roulette may terminate it.
+    pub min_depth: i32,
     pub defocus_angle: f32,
     pub focus_distance: f32,
     pub background: Color,
-    pixel_samples_scale: f32,
+    /// Times at which the shutter opens and closes. Rays are spread over this interval.
+    pub shutter_open: f32,
+    pub shutter_close: f32,
+    pub shutter_curve: ShutterCurve,
     image_height: i32,
+    viewport_width: f32,
+    viewport_height: f32,
+    defocus_radius: f32,
     center: Vec3,
     pixel00: Vec3,
     delta_u: Vec3,
@@ -28,17 +51,16 @@ pub struct Camera {
 }
 
 impl Camera {
+    /// Camera placed by `pose`, as for [`set_pose`](Camera::set_pose), with a vertical field of
+    /// view of `vfov` degrees. Objects at `focus_distance` are sharp, and a `defocus_angle` above
+    /// zero blurs everything else.
     pub fn new(
         focus_distance: f32,
         defocus_angle: f32,
         vfov: f32,
-        from: Vec3,
-        at: Vec3,
-        up: Vec3,
+        pose: &Mat4,
         aspect_ratio: f32,
         image_width: i32,
-        samples_per_pixel: i32,
-        max_depth: i32,
     ) -> Self {
         let mut image_height = (image_width as f32 / aspect_ratio) as i32;
         image_height = if image_height < 1 { 1 } else { image_height };
@@ -49,50 +71,66 @@ impl Camera {
         let viewport_height = 2.0 * h * focus_distance;
         let viewport_width = viewport_height * (image_width as f32 / image_height as f32);
 
-        let w = (from - at).normalize();
-        let u = up.cross(w).normalize();
-        let v = w.cross(u);
-
-        let viewport_u = viewport_width * u;
-        let viewport_v = viewport_height * -v;
-
-        let delta_u = viewport_u / image_width as f32;
-        let delta_v = viewport_v / image_height as f32;
-
-        let viewport00 = from - focus_distance * w - viewport_u / 2.0 - viewport_v / 2.0;
-        let pixel00 = viewport00 + 0.5 * (delta_u + delta_v);
-
         let defocus_radius =
             focus_distance * f32::tan(defocus_angle * std::f32::consts::PI / 360.0);
 
-        Camera {
-            aspect_ratio,
+        let mut camera = Camera {
             image_width,
-            samples_per_pixel,
-            pixel_samples_scale: 1.0 / samples_per_pixel as f32,
-            max_depth,
+            samples_per_pixel: 100,
+            max_depth: 50,
+            min_depth: 3,
             image_height,
+            viewport_width,
+            viewport_height,
+            defocus_radius,
             defocus_angle,
             focus_distance,
-            center: from,
-            pixel00,
-            delta_u,
-            delta_v,
-            defocus_u: u * defocus_radius,
-            defocus_v: v * defocus_radius,
+            center: Vec3::new_zero(),
+            pixel00: Vec3::new_zero(),
+            delta_u: Vec3::new_zero(),
+            delta_v: Vec3::new_zero(),
+            defocus_u: Vec3::new_zero(),
+            defocus_v: Vec3::new_zero(),
             background: Color::new(0.70, 0.80, 1.00),
-        }
+            shutter_open: 0.0,
+            shutter_close: 1.0,
+            shutter_curve: ShutterCurve::Box,
+        };
+        camera.set_pose(pose);
+        camera
     }
 
-    pub fn render(&self, world: &HittableList, disk_sampling: bool) {
-        let mut string: String = "".to_string();
+    /// Moves and turns the camera. `pose` maps a camera at the origin looking down -z with +y up
+    /// into the world, as [`Mat4::look_at`] does; its linear part must be a rotation.
+    pub fn set_pose(&mut self, pose: &Mat4) {
+        let basis = pose.linear();
+        let (u, v, w) = (basis.column(0), basis.column(1), basis.column(2));
+        let from = pose.translation_part();
+
+        let viewport_u = self.viewport_width * u;
+        let viewport_v = self.viewport_height * -v;
+
+        self.delta_u = viewport_u / self.image_width as f32;
+        self.delta_v = viewport_v / self.image_height as f32;
+
+        let viewport00 = from - self.focus_distance * w - viewport_u / 2.0 - viewport_v / 2.0;
+        self.pixel00 = viewport00 + 0.5 * (self.delta_u + self.delta_v);
 
-        string.push_str(&format!(
-            "P3\n{} {}\n{}\n",
-            self.image_width, self.image_height, 255
-        ));
+        self.center = from;
+        self.defocus_u = u * self.defocus_radius;
+        self.defocus_v = v * self.defocus_radius;
+    }
 
-        let start = Instant::now();
+    /// Traces `samples_per_pixel` rays through every pixel in parallel. With `disk_sampling` the
+    /// samples are spread over a disk instead of a square around the pixel center. Emitters in
+    /// `lights` must also be part of `world`; they are sampled directly at every diffuse bounce.
+    pub fn render(
+        &self,
+        world: &dyn Hittable,
+        lights: &HittableList,
+        disk_sampling: bool,
+    ) -> Framebuffer {
+        let pixel_samples_scale = 1.0 / self.samples_per_pixel as f32;
 
         let pixels = (0..self.image_height)
             .into_par_iter()
@@ -105,42 +143,122 @@ impl Camera {
 
                         for _ in 0..self.samples_per_pixel {
                             let ray = self.get_ray(w, h, disk_sampling, rng);
-                            pixel_color = pixel_color + self.ray_color(&ray, self.max_depth, world);
+                            pixel_color = pixel_color + self.ray_color(&ray, world, lights, rng);
                         }
-                        write_color(&(pixel_color * self.pixel_samples_scale))
+                        pixel_color * pixel_samples_scale
                     })
-                    .collect::<Vec<String>>()
-                    .join("")
+                    .collect::<Vec<Color>>()
             })
-            .collect::<Vec<String>>()
-            .join("");
+            .flatten()
+            .collect::<Vec<Color>>();
 
-        println!("Done in {:?}", start.elapsed());
-        string.push_str(pixels.as_str());
-        fs::write("final_image.ppm", string).unwrap();
+        Framebuffer::from_pixels(self.image_width as u32, self.image_height as u32, pixels)
     }
 
-    fn ray_color(&self, ray: &Ray, max_depth: i32, world: &dyn Hittable) -> Color {
-        if max_depth <= 0 {
-            return Color::new(0.0, 0.0, 0.0);
-        }
+    // Follows a path from the camera, accumulating the light reaching it scaled by the product of
+    // the attenuations so far. `scatter_pdf` is the density with which the previous bounce picked
+    // `ray` when it also sampled the lights directly. Emission found by `ray` is then weighted
+    // against the chance of light sampling having found it, using the power heuristic.
+    fn ray_color(
+        &self,
+        ray: &Ray,
+        world: &dyn Hittable,
+        lights: &HittableList,
+        rng: &mut ThreadRng,
+    ) -> Color {
+        let mut ray = *ray;
+        let mut radiance = Color::new_zero();
+        let mut throughput = Color::new(1.0, 1.0, 1.0);
+        let mut scatter_pdf = None;
+
+        for depth in 0..self.max_depth {
+            let Some(hit_record) = world.hit(&ray, 0.001, f32::INFINITY) else {
+                radiance = radiance + throughput * self.background;
+                break;
+            };
+
+            let mut emitted =
+                hit_record
+                    .material
+                    .emitted(hit_record.u, hit_record.v, &hit_record.point);
+            if let Some(scatter_pdf) = scatter_pdf {
+                let light_pdf = lights.pdf(&ray.origin, &ray.direction, ray.time);
+                emitted *= power_heuristic(scatter_pdf, light_pdf);
+            }
+            radiance = radiance + throughput * emitted;
+
+            let Some((scattered, attenuation)) = hit_record.material.scatter(&ray, &hit_record)
+            else {
+                break;
+            };
 
-        match world.hit(ray, 0.001, f32::INFINITY) {
-            Some(hit_record) => {
-                let color_from_emission =
+            scatter_pdf = if !lights.objects.is_empty() && !hit_record.material.is_specular() {
+                radiance =
+                    radiance + throughput * self.direct_light(&ray, &hit_record, world, lights);
+                Some(
                     hit_record
                         .material
-                        .emitted(hit_record.u, hit_record.u, &hit_record.point);
-                let result = hit_record.material.scatter(ray, &hit_record);
+                        .pdf(&ray, &hit_record, &scattered.direction),
+                )
+            } else {
+                None
+            };
 
-                if result.is_none() {
-                    return color_from_emission;
+            throughput = throughput * attenuation;
+
+            // Russian roulette: past `min_depth` a path survives with a probability that follows
+            // its throughput, and survivors are scaled up so the estimate stays unbiased.
+            if depth + 1 >= self.min_depth {
+                let survival = throughput
+                    .x()
+                    .max(throughput.y())
+                    .max(throughput.z())
+                    .min(0.95);
+                if survival <= 0.0 || rng.gen::<f32>() >= survival {
+                    break;
                 }
-                let result = result.unwrap();
-                color_from_emission + result.1 * self.ray_color(&result.0, max_depth - 1, world)
+                throughput = throughput / survival;
             }
-            None => self.background,
+
+            ray = scattered;
+        }
+
+        radiance
+    }
+
+    // Next event estimation: light arriving at the hit point along a shadow ray towards a random
+    // point on one of the lights, attenuated by any media in between and weighted against the
+    // chance of `scatter` picking the same ray.
+    fn direct_light(
+        &self,
+        ray: &Ray,
+        hit_record: &HitRecord,
+        world: &dyn Hittable,
+        lights: &HittableList,
+    ) -> Color {
+        let direction = lights.sample(&hit_record.point, ray.time);
+        let pdf = lights.pdf(&hit_record.point, &direction, ray.time);
+        if pdf <= 0.0 {
+            return Color::new_zero();
+        }
+
+        let scattering = hit_record.material.eval(ray, hit_record, &direction);
+        if scattering.near_zero() {
+            return Color::new_zero();
+        }
+
+        let shadow_ray = Ray::new(hit_record.point, direction, ray.time);
+        let Some(light) = lights.hit(&shadow_ray, 0.001, f32::INFINITY) else {
+            return Color::new_zero();
+        };
+        let transmittance = world.transmittance(&shadow_ray, 0.001, light.t * (1.0 - 1e-4));
+        if transmittance <= 0.0 {
+            return Color::new_zero();
         }
+
+        let emitted = light.material.emitted(light.u, light.v, &light.point);
+        let scatter_pdf = hit_record.material.pdf(ray, hit_record, &direction);
+        scattering * emitted * (transmittance * power_heuristic(pdf, scatter_pdf) / pdf)
     }
 
     fn get_ray(&self, i: i32, j: i32, disk_sampling: bool, rng: &mut ThreadRng) -> Ray {
@@ -160,7 +278,24 @@ impl Camera {
             self.sample_defocus_disk(rng)
         };
 
-        Ray::new(ray_origin, pixel_sample - ray_origin, rng.gen::<f32>())
+        Ray::new(
+            ray_origin,
+            pixel_sample - ray_origin,
+            self.sample_time(j, rng),
+        )
+    }
+
+    fn sample_time(&self, row: i32, rng: &mut ThreadRng) -> f32 {
+        let fraction = match self.shutter_curve {
+            ShutterCurve::Box => rng.gen::<f32>(),
+            // The mean of two uniform numbers has a triangular distribution.
+            ShutterCurve::Triangle => 0.5 * (rng.gen::<f32>() + rng.gen::<f32>()),
+            ShutterCurve::Rolling { exposure } => {
+                let start = (1.0 - exposure) * row as f32 / (self.image_height - 1).max(1) as f32;
+                start + exposure * rng.gen::<f32>()
+            }
+        };
+        self.shutter_open + (self.shutter_close - self.shutter_open) * fraction
     }
 
     fn sample_square(rng: &mut ThreadRng) -> Vec3 {
@@ -184,3 +319,53 @@ impl Camera {
     }
 }
 
+// Weight of a sample drawn with `pdf` when `other_pdf` could have produced it as well.
+fn power_heuristic(pdf: f32, other_pdf: f32) -> f32 {
+    if other_pdf <= 0.0 {
+        return 1.0;
+    }
+    let (a, b) = (pdf * pdf, other_pdf * other_pdf);
+    a / (a + b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vec3::Point3;
+
+    #[test]
+    fn rolling_shutter_exposes_rows_in_turn_within_the_interval() {
+        let pose = Mat4::look_at(
+            Point3::new(0.0, 0.0, 1.0),
+            Point3::new(0.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+        );
+        let mut camera = Camera::new(10.0, 0.0, 40.0, &pose, 1.0, 10);
+        camera.shutter_open = 2.0;
+        camera.shutter_close = 3.0;
+        camera.shutter_curve = ShutterCurve::Rolling { exposure: 0.25 };
+
+        let rng = &mut thread_rng();
+        let last = camera.image_height - 1;
+        for row in 0..=last {
+            let times: Vec<f32> = (0..2000).map(|_| camera.sample_time(row, rng)).collect();
+            let earliest = times.iter().copied().fold(f32::INFINITY, f32::min);
+            let latest = times.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+
+            assert!(
+                2.0 <= earliest && latest <= 3.0,
+                "row {row}: {earliest}..{latest}"
+            );
+            assert!(
+                latest - earliest <= 0.25 + 1e-5,
+                "row {row}: {earliest}..{latest}"
+            );
+            if row == 0 {
+                assert!(earliest < 2.01, "{earliest}");
+            }
+            if row == last {
+                assert!(latest > 2.99, "{latest}");
+            }
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::disk::{circle_bbox, Disk};
+use crate::hittable::{HitRecord, Hittable};
+use crate::material::Material;
+use crate::onb::Onb;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+
+/// Circular cone narrowing from a base of `radius` to its `apex`, optionally closed by a disk at
+/// the base. On the side `u` runs around the axis and `v` from the base to the apex.
+pub struct Cone<T: Material>
+{
+    base: Vec3,
+    onb: Onb,
+    height: f32,
+    // Radius lost per unit of height.
+    slope: f32,
+    material: Arc<T>,
+    cap: Option<Disk<Arc<T>>>,
+    bbox: Aabb,
+}
+
+impl<T: Material> Cone<T>
+{
+    /// Panics unless `base` and `apex` are apart and `radius` is positive.
+    pub fn new(base: Vec3, apex: Vec3, radius: f32, capped: bool, material: T) -> Cone<T>
+    {
+        let axis = apex - base;
+        let height = axis.length();
+        assert!(height > 0.0 && radius > 0.0, "a cone needs a positive height and radius, got {height} and {radius}");
+        let material = Arc::new(material);
+        let cap = capped.then(|| Disk::new(base, -axis, radius, material.clone()));
+        let bbox = Aabb::surrounding(&circle_bbox(base, axis, radius), &Aabb::new(apex, apex));
+        Cone { base, onb: Onb::new(&axis), height, slope: radius / height, material, cap, bbox }
+    }
+
+    // Points on the side satisfy x² + y² = (slope · (height - z))² in the local frame.
+    fn hit_side(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>>
+    {
+        let origin = self.onb.to_local(ray.origin - self.base);
+        let direction = self.onb.to_local(ray.direction);
+        let k = self.slope * self.slope;
+        let h = self.height - origin.z();
+
+        let a = direction.x() * direction.x() + direction.y() * direction.y() - k * direction.z() * direction.z();
+        let half_b = origin.x() * direction.x() + origin.y() * direction.y() + k * h * direction.z();
+        let c = origin.x() * origin.x() + origin.y() * origin.y() - k * h * h;
+
+        let roots = if a.abs() < 1e-12
+        {
+            // The ray is parallel to the side and crosses it at most once.
+            if half_b.abs() < 1e-12 { return None; }
+            [-c / (2.0 * half_b); 2]
+        } else {
+            let discriminant = half_b * half_b - a * c;
+            if discriminant < 0.0 { return None; }
+            let sqrt = discriminant.sqrt();
+            let (t0, t1) = ((-half_b - sqrt) / a, (-half_b + sqrt) / a);
+            [t0.min(t1), t0.max(t1)]
+        };
+
+        for t in roots {
+            let z = origin.z() + t * direction.z();
+            if t <= t_min || t >= t_max || !(0.0..=self.height).contains(&z)
+            {
+                continue;
+            }
+
+            let local = origin + t * direction;
+            let normal = Vec3::new(local.x(), local.y(), k * (self.height - z));
+            let mut hit_record = HitRecord {
+                point: ray.at(t),
+                normal: self.onb.transform(normal).normalize(),
+                t,
+                material: &self.material,
+                front_face: false,
+                u: local.y().atan2(local.x()) / std::f32::consts::TAU + 0.5,
+                v: z / self.height,
+            };
+            hit_record.set_normal(ray);
+            return Some(hit_record);
+        }
+
+        None
+    }
+}
+
+impl<T: Material> Hittable for Cone<T> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        let side = self.hit_side(ray, t_min, t_max);
+        let closest = side.as_ref().map_or(t_max, |h| h.t);
+
+        match self.cap.as_ref().and_then(|cap| cap.hit(ray, t_min, closest)) {
+            Some(hit_record) => Some(hit_record),
+            None => side,
+        }
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+}
@@ -0,0 +1,77 @@
+use rand::Rng;
+
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+
+// Part of `t_min..t_max` where the ray is inside a convex boundary, found from where it enters and
+// leaves the boundary. Only the part in front of the ray origin counts.
+pub(crate) fn boundary_interval(boundary: &dyn Hittable, ray: &Ray, t_min: f32, t_max: f32) -> Option<(f32, f32)>
+{
+    let entry = boundary.hit(ray, f32::NEG_INFINITY, f32::INFINITY)?;
+    let exit = boundary.hit(ray, entry.t + 0.0001, f32::INFINITY)?;
+
+    let t_entry = entry.t.max(t_min).max(0.0);
+    let t_exit = exit.t.min(t_max);
+    (t_entry < t_exit).then_some((t_entry, t_exit))
+}
+
+/// Fog or smoke of uniform density filling a closed boundary. Rays scatter at a random distance
+/// inside it according to the phase function, which is usually [`Isotropic`](crate::material::Isotropic).
+pub struct ConstantMedium<H: Hittable, M: Material>
+{
+    boundary: H,
+    neg_inv_density: f32,
+    phase_function: M,
+}
+
+impl<H: Hittable, M: Material> ConstantMedium<H, M>
+{
+    /// Panics unless `density` is positive.
+    pub fn new(boundary: H, density: f32, phase_function: M) -> ConstantMedium<H, M>
+    {
+        assert!(density > 0.0, "medium density must be positive, got {density}");
+        ConstantMedium { boundary, neg_inv_density: -1.0 / density, phase_function }
+    }
+}
+
+impl<H: Hittable, M: Material> Hittable for ConstantMedium<H, M> {
+    // Scatters after an exponentially distributed distance if that falls inside the boundary.
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        let (t_entry, t_exit) = boundary_interval(&self.boundary, ray, t_min, t_max)?;
+
+        let ray_length = ray.direction.length();
+        let distance_inside_boundary = (t_exit - t_entry) * ray_length;
+        let hit_distance = self.neg_inv_density * rand::thread_rng().gen::<f32>().ln();
+        if hit_distance > distance_inside_boundary
+        {
+            return None;
+        }
+
+        let t = t_entry + hit_distance / ray_length;
+        Some(HitRecord {
+            point: ray.at(t),
+            // Volumes have no surface, so the normal and side are arbitrary.
+            normal: Vec3::new(1.0, 0.0, 0.0),
+            t,
+            material: &self.phase_function,
+            front_face: true,
+            u: 0.0,
+            v: 0.0,
+        })
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.boundary.bounding_box()
+    }
+
+    // Beer–Lambert attenuation over the distance travelled inside the boundary.
+    fn transmittance(&self, ray: &Ray, t_min: f32, t_max: f32) -> f32 {
+        match boundary_interval(&self.boundary, ray, t_min, t_max) {
+            Some((t_entry, t_exit)) => ((t_exit - t_entry) * ray.direction.length() / self.neg_inv_density).exp(),
+            None => 1.0,
+        }
+    }
+}
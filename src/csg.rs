@@ -0,0 +1,276 @@
+use crate::aabb::Aabb;
+use crate::hittable::{HitInterval, HitRecord, Hittable};
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+
+/// How a [`Csg`] node combines the insides of its two objects.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CsgOperation
+{
+    Union,
+    Intersection,
+    /// Everything inside the first object but not inside the second.
+    Difference,
+}
+
+impl CsgOperation
+{
+    fn contains(self, inside_a: bool, inside_b: bool) -> bool
+    {
+        match self {
+            CsgOperation::Union => inside_a || inside_b,
+            CsgOperation::Intersection => inside_a && inside_b,
+            CsgOperation::Difference => inside_a && !inside_b,
+        }
+    }
+}
+
+/// Constructive solid geometry: the union, intersection or difference of two closed objects.
+/// Each part of the surface keeps the material of the object it came from.
+pub struct Csg<A: Hittable, B: Hittable>
+{
+    operation: CsgOperation,
+    a: A,
+    b: B,
+    bbox: Aabb,
+}
+
+impl<A: Hittable, B: Hittable> Csg<A, B>
+{
+    pub fn new(operation: CsgOperation, a: A, b: B) -> Csg<A, B>
+    {
+        let (box_a, box_b) = (a.bounding_box(), b.bounding_box());
+        let bbox = match operation {
+            CsgOperation::Union => Aabb::surrounding(&box_a, &box_b),
+            CsgOperation::Intersection => overlap(&box_a, &box_b),
+            CsgOperation::Difference => box_a,
+        };
+        Csg { operation, a, b, bbox }
+    }
+}
+
+fn overlap(a: &Aabb, b: &Aabb) -> Aabb
+{
+    let min = Vec3::new(a.min.x().max(b.min.x()), a.min.y().max(b.min.y()), a.min.z().max(b.min.z()));
+    let max = Vec3::new(a.max.x().min(b.max.x()), a.max.y().min(b.max.y()), a.max.z().min(b.max.z()));
+    if min.x() > max.x() || min.y() > max.y() || min.z() > max.z() { Aabb::EMPTY } else { Aabb { min, max } }
+}
+
+// Every surface crossing of the intervals, tagged with the object it belongs to.
+fn boundaries(intervals: Vec<HitInterval<'_>>, is_a: bool) -> impl Iterator<Item = (HitRecord<'_>, bool)>
+{
+    intervals.into_iter()
+        .flat_map(|interval| [interval.enter, interval.exit])
+        .flatten()
+        .map(move |hit_record| (hit_record, is_a))
+}
+
+// Whether the ray starts inside `object`, given its intervals up to `t_max`. Without any boundary
+// in range the ray is either outside or inside throughout, which the next crossing tells apart.
+fn starts_inside(object: &impl Hittable, intervals: &[HitInterval<'_>], ray: &Ray, t_max: f32) -> bool
+{
+    match intervals.first() {
+        Some(interval) => interval.enter.is_none(),
+        None => object.hit(ray, t_max, f32::INFINITY).is_some_and(|hit_record| !hit_record.front_face),
+    }
+}
+
+impl<A: Hittable, B: Hittable> Csg<A, B>
+{
+    // Walks along the ray through the boundaries of both objects, keeping track of which ones
+    // the ray is inside. Returns whether it starts inside the combination and, lazily, the
+    // boundaries where that changes, with `front_face` telling whether the ray enters there.
+    fn crossings(&self, ray: &Ray, t_min: f32, t_max: f32) -> (bool, impl Iterator<Item = HitRecord<'_>> + '_)
+    {
+        let a = self.a.hit_intervals(ray, t_min, t_max);
+        let b = self.b.hit_intervals(ray, t_min, t_max);
+        let mut inside_a = starts_inside(&self.a, &a, ray, t_max);
+        let mut inside_b = starts_inside(&self.b, &b, ray, t_max);
+
+        let mut events: Vec<_> = boundaries(a, true).chain(boundaries(b, false)).collect();
+        events.sort_by(|(x, _), (y, _)| x.t.total_cmp(&y.t));
+
+        let operation = self.operation;
+        let mut inside = operation.contains(inside_a, inside_b);
+        let starts_inside = inside;
+
+        let crossings = events.into_iter().filter_map(move |(mut hit_record, is_a)| {
+            if is_a { inside_a = hit_record.front_face } else { inside_b = hit_record.front_face }
+
+            let now = operation.contains(inside_a, inside_b);
+            if now == inside
+            {
+                return None;
+            }
+            inside = now;
+
+            // The normal already faces against the ray; only the side needs to follow the
+            // combination, since the second object of a difference is seen from within.
+            hit_record.front_face = now;
+            Some(hit_record)
+        });
+        (starts_inside, crossings)
+    }
+}
+
+impl<A: Hittable, B: Hittable> Hittable for Csg<A, B> {
+    // Only the first boundary is needed, so the walk stops there.
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        if !self.bbox.hit(ray, t_min, t_max)
+        {
+            return None;
+        }
+        self.crossings(ray, t_min, t_max).1.next()
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    fn hit_intervals(&self, ray: &Ray, t_min: f32, t_max: f32) -> Vec<HitInterval<'_>> {
+        if !self.bbox.hit(ray, t_min, t_max)
+        {
+            return Vec::new();
+        }
+
+        let (starts_inside, crossings) = self.crossings(ray, t_min, t_max);
+        let mut open = starts_inside.then_some(HitInterval { enter: None, exit: None });
+        let mut intervals = Vec::new();
+
+        for hit_record in crossings {
+            if hit_record.front_face
+            {
+                open = Some(HitInterval { enter: Some(hit_record), exit: None });
+            } else {
+                intervals.push(HitInterval { enter: open.take().and_then(|interval| interval.enter), exit: Some(hit_record) });
+            }
+        }
+
+        intervals.extend(open);
+        intervals
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::material::Lambertian;
+    use crate::sphere::Sphere;
+    use crate::texture::SolidColor;
+
+    type Ball = Sphere<Lambertian<SolidColor>>;
+
+    fn ball(x: f32, radius: f32) -> Ball
+    {
+        Sphere::new(Vec3::new(x, 0.0, 0.0), radius, Lambertian::new(SolidColor::new(0.5, 0.5, 0.5)))
+    }
+
+    // A spans -1..1 and B 0..2 along the x axis.
+    fn csg(operation: CsgOperation) -> Csg<Ball, Ball>
+    {
+        Csg::new(operation, ball(0.0, 1.0), ball(1.0, 1.0))
+    }
+
+    fn along_x(x: f32, direction: f32) -> Ray
+    {
+        Ray::new(Vec3::new(x, 0.0, 0.0), Vec3::new(direction, 0.0, 0.0), 0.0)
+    }
+
+    fn assert_intervals(object: &impl Hittable, ray: &Ray, t_max: f32, expected: &[(Option<f32>, Option<f32>)])
+    {
+        let intervals: Vec<_> = object.hit_intervals(ray, 0.001, t_max).iter()
+            .map(|interval| (interval.enter.as_ref().map(|hit| hit.t), interval.exit.as_ref().map(|hit| hit.t)))
+            .collect();
+        let close = |a: Option<f32>, b: Option<f32>| match (a, b) {
+            (Some(a), Some(b)) => (a - b).abs() < 1e-4,
+            (a, b) => a.is_none() && b.is_none(),
+        };
+        assert!(
+            intervals.len() == expected.len() && intervals.iter().zip(expected).all(|(x, y)| close(x.0, y.0) && close(x.1, y.1)),
+            "{intervals:?} != {expected:?}"
+        );
+    }
+
+    fn assert_hit(object: &impl Hittable, ray: &Ray, t: f32, normal: Vec3, front_face: bool)
+    {
+        let hit = object.hit(ray, 0.001, f32::INFINITY).expect("ray missed");
+        assert!((hit.t - t).abs() < 1e-4, "{} != {t}", hit.t);
+        assert!((hit.normal - normal).length() < 1e-4, "{:?} != {normal:?}", hit.normal);
+        assert_eq!(hit.front_face, front_face);
+    }
+
+    #[test]
+    fn operations_on_two_spheres()
+    {
+        let ray = along_x(-5.0, 1.0);
+        let left = Vec3::new(-1.0, 0.0, 0.0);
+
+        let union = csg(CsgOperation::Union);
+        assert_intervals(&union, &ray, f32::INFINITY, &[(Some(4.0), Some(7.0))]);
+        assert_hit(&union, &ray, 4.0, left, true);
+
+        let intersection = csg(CsgOperation::Intersection);
+        assert_intervals(&intersection, &ray, f32::INFINITY, &[(Some(5.0), Some(6.0))]);
+        assert_hit(&intersection, &ray, 5.0, left, true);
+
+        let difference = csg(CsgOperation::Difference);
+        assert_intervals(&difference, &ray, f32::INFINITY, &[(Some(4.0), Some(5.0))]);
+        assert_hit(&difference, &ray, 4.0, left, true);
+    }
+
+    #[test]
+    fn difference_shows_the_inside_of_the_second_sphere()
+    {
+        // Coming from +x the ray passes through B first and meets A - B where it leaves B.
+        let difference = csg(CsgOperation::Difference);
+        let ray = along_x(5.0, -1.0);
+        assert_intervals(&difference, &ray, f32::INFINITY, &[(Some(5.0), Some(6.0))]);
+        assert_hit(&difference, &ray, 5.0, Vec3::new(1.0, 0.0, 0.0), true);
+    }
+
+    #[test]
+    fn rays_starting_inside_an_operand()
+    {
+        let ray = along_x(0.5, 1.0);
+        assert_hit(&csg(CsgOperation::Union), &ray, 1.5, Vec3::new(-1.0, 0.0, 0.0), false);
+        assert_hit(&csg(CsgOperation::Intersection), &ray, 0.5, Vec3::new(-1.0, 0.0, 0.0), false);
+        assert!(csg(CsgOperation::Difference).hit(&ray, 0.001, f32::INFINITY).is_none());
+
+        let ray = along_x(-0.5, 1.0);
+        assert_intervals(&csg(CsgOperation::Difference), &ray, f32::INFINITY, &[(None, Some(0.5))]);
+    }
+
+    #[test]
+    fn rays_ending_before_any_boundary_stay_inside()
+    {
+        // Neither operand is crossed before t_max, so only what lies beyond tells the ray is inside.
+        let ray = along_x(0.5, 1.0);
+        assert_intervals(&csg(CsgOperation::Intersection), &ray, 0.25, &[(None, None)]);
+        assert_intervals(&csg(CsgOperation::Union), &ray, 0.25, &[(None, None)]);
+        assert_intervals(&csg(CsgOperation::Difference), &ray, 0.25, &[]);
+
+        let ray = along_x(-0.5, 1.0);
+        assert_intervals(&csg(CsgOperation::Difference), &ray, 0.25, &[(None, None)]);
+        assert!(csg(CsgOperation::Difference).hit(&ray, 0.001, 0.25).is_none());
+    }
+
+    #[test]
+    fn nested_combinations()
+    {
+        // A small sphere spanning 0.25..0.75 is cut out of the union.
+        let nested = Csg::new(CsgOperation::Difference, csg(CsgOperation::Union), ball(0.5, 0.25));
+
+        let ray = along_x(-5.0, 1.0);
+        assert_intervals(&nested, &ray, f32::INFINITY, &[(Some(4.0), Some(5.25)), (Some(5.75), Some(7.0))]);
+
+        // Starting in the hole, the ray enters the outer combination where it leaves the small sphere.
+        let ray = along_x(0.5, 1.0);
+        assert_intervals(&nested, &ray, f32::INFINITY, &[(Some(0.25), Some(1.5))]);
+        assert_hit(&nested, &ray, 0.25, Vec3::new(-1.0, 0.0, 0.0), true);
+        assert_intervals(&nested, &ray, 0.1, &[]);
+
+        let ray = along_x(0.1, 1.0);
+        assert_intervals(&nested, &ray, 0.1, &[(None, None)]);
+    }
+}
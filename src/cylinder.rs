@@ -0,0 +1,98 @@
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::disk::{circle_bbox, Disk};
+use crate::hittable::{HitRecord, Hittable};
+use crate::material::Material;
+use crate::onb::Onb;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+
+/// Circular cylinder between the centers of its `base` and `top`, optionally closed by disks.
+/// On the side `u` runs around the axis and `v` from the base to the top.
+pub struct Cylinder<T: Material>
+{
+    base: Vec3,
+    onb: Onb,
+    height: f32,
+    radius: f32,
+    material: Arc<T>,
+    caps: Option<[Disk<Arc<T>>; 2]>,
+    bbox: Aabb,
+}
+
+impl<T: Material> Cylinder<T>
+{
+    /// Panics unless `base` and `top` are apart and `radius` is positive.
+    pub fn new(base: Vec3, top: Vec3, radius: f32, capped: bool, material: T) -> Cylinder<T>
+    {
+        let axis = top - base;
+        let height = axis.length();
+        assert!(height > 0.0 && radius > 0.0, "a cylinder needs a positive height and radius, got {height} and {radius}");
+        let material = Arc::new(material);
+        let caps = capped.then(|| [
+            Disk::new(base, -axis, radius, material.clone()),
+            Disk::new(top, axis, radius, material.clone()),
+        ]);
+        let bbox = Aabb::surrounding(&circle_bbox(base, axis, radius), &circle_bbox(top, axis, radius));
+        Cylinder { base, onb: Onb::new(&axis), height, radius, material, caps, bbox }
+    }
+
+    fn hit_side(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>>
+    {
+        let origin = self.onb.to_local(ray.origin - self.base);
+        let direction = self.onb.to_local(ray.direction);
+
+        let a = direction.x() * direction.x() + direction.y() * direction.y();
+        if a < 1e-12 { return None; }
+
+        let half_b = origin.x() * direction.x() + origin.y() * direction.y();
+        let c = origin.x() * origin.x() + origin.y() * origin.y() - self.radius * self.radius;
+        let discriminant = half_b * half_b - a * c;
+        if discriminant < 0.0 { return None; }
+
+        let sqrt = discriminant.sqrt();
+        for t in [(-half_b - sqrt) / a, (-half_b + sqrt) / a] {
+            let z = origin.z() + t * direction.z();
+            if t <= t_min || t >= t_max || !(0.0..=self.height).contains(&z)
+            {
+                continue;
+            }
+
+            let local = origin + t * direction;
+            let mut hit_record = HitRecord {
+                point: ray.at(t),
+                normal: self.onb.transform(Vec3::new(local.x(), local.y(), 0.0) / self.radius),
+                t,
+                material: &self.material,
+                front_face: false,
+                u: local.y().atan2(local.x()) / std::f32::consts::TAU + 0.5,
+                v: z / self.height,
+            };
+            hit_record.set_normal(ray);
+            return Some(hit_record);
+        }
+
+        None
+    }
+}
+
+impl<T: Material> Hittable for Cylinder<T> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        let mut res = self.hit_side(ray, t_min, t_max);
+
+        for cap in self.caps.iter().flatten() {
+            let closest = res.as_ref().map_or(t_max, |h| h.t);
+            if let Some(hit_record) = cap.hit(ray, t_min, closest)
+            {
+                res = Some(hit_record);
+            }
+        }
+
+        res
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+}
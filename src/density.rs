@@ -0,0 +1,176 @@
+use std::fs;
+use std::io::{Error, ErrorKind};
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::perlin::Perlin;
+use crate::vec3::Vec3;
+
+/// Spatially varying extinction coefficient of a participating medium.
+pub trait DensityField: Sync + Send
+{
+    fn density(&self, point: &Vec3) -> f32;
+    /// Upper bound of `density` over the whole field, used as the majorant for delta tracking.
+    fn max_density(&self) -> f32;
+}
+
+impl<D: DensityField + ?Sized> DensityField for Arc<D>
+{
+    fn density(&self, point: &Vec3) -> f32 {
+        (**self).density(point)
+    }
+
+    fn max_density(&self) -> f32 {
+        (**self).max_density()
+    }
+}
+
+/// Wispy density from Perlin turbulence, ranging from zero up to twice `density`.
+#[derive(Clone)]
+pub struct NoiseDensity
+{
+    perlin_noise: Perlin,
+    scale: f32,
+    density: f32,
+    depth: i32,
+}
+
+impl NoiseDensity
+{
+    /// `scale` is the frequency of the noise and `depth` the number of turbulence octaves.
+    /// Panics unless `density` is positive.
+    pub fn new(scale: f32, density: f32, depth: i32) -> NoiseDensity
+    {
+        assert!(density > 0.0, "noise density must be positive, got {density}");
+        NoiseDensity { perlin_noise: Perlin::new(), scale, density, depth }
+    }
+}
+
+impl DensityField for NoiseDensity
+{
+    // Turbulence stays below 2 in practice; clamping makes the majorant a strict bound.
+    fn density(&self, point: &Vec3) -> f32 {
+        self.density * self.perlin_noise.turbulence(&(self.scale * *point), self.depth).min(2.0)
+    }
+
+    fn max_density(&self) -> f32 {
+        2.0 * self.density
+    }
+}
+
+/// Densities stored on a regular grid spanning `bounds`, interpolated trilinearly between the
+/// voxel centers. Points outside the grid have zero density.
+pub struct VoxelGrid
+{
+    resolution: [usize; 3],
+    values: Vec<f32>,
+    bounds: Aabb,
+    max_density: f32,
+}
+
+impl VoxelGrid
+{
+    /// `values` are ordered with x varying fastest, then y, then z.
+    pub fn new(resolution: [usize; 3], values: Vec<f32>, bounds: Aabb) -> VoxelGrid
+    {
+        assert!(resolution.iter().all(|&r| r > 0), "voxel grid resolution must be positive");
+        assert_eq!(values.len(), resolution.iter().product::<usize>(), "voxel count does not match the resolution");
+        let max_density = values.iter().fold(0.0f32, |max, &v| max.max(v));
+        VoxelGrid { resolution, values, bounds, max_density }
+    }
+
+    /// Reads a Mitsuba `.vol` grid of 32-bit floats. Only the first channel is used.
+    pub fn load(path: &str) -> std::io::Result<VoxelGrid>
+    {
+        let bytes = fs::read(path)?;
+        let invalid = |message: &str| Error::new(ErrorKind::InvalidData, message.to_string());
+
+        if bytes.len() < 48 || &bytes[0..3] != b"VOL" || bytes[3] != 3
+        {
+            return Err(invalid("not a version 3 VOL file"));
+        }
+
+        let word = |i: usize| <[u8; 4]>::try_from(&bytes[4 + 4 * i..8 + 4 * i]).unwrap();
+        let int = |i: usize| i32::from_le_bytes(word(i));
+        let float = |i: usize| f32::from_le_bytes(word(i));
+
+        if int(0) != 1
+        {
+            return Err(invalid("only float32 VOL grids are supported"));
+        }
+
+        let resolution = [int(1), int(2), int(3)].map(|r| r.max(0) as usize);
+        let channels = int(4).max(1) as usize;
+        let bounds = Aabb::new(Vec3::new(float(5), float(6), float(7)), Vec3::new(float(8), float(9), float(10)));
+
+        let count = resolution.iter().product::<usize>();
+        if count == 0
+        {
+            return Err(invalid("VOL grid is empty"));
+        }
+        let data = &bytes[48..];
+        if data.len() < 4 * count * channels
+        {
+            return Err(invalid("VOL file is truncated"));
+        }
+
+        let values = data.chunks_exact(4 * channels)
+            .take(count)
+            .map(|voxel| f32::from_le_bytes(voxel[0..4].try_into().unwrap()))
+            .collect();
+        Ok(VoxelGrid::new(resolution, values, bounds))
+    }
+
+    /// Multiplies every density by `factor`. Panics unless `factor` is positive.
+    pub fn scaled(mut self, factor: f32) -> VoxelGrid
+    {
+        assert!(factor > 0.0, "voxel density scale must be positive, got {factor}");
+        self.values.iter_mut().for_each(|v| *v *= factor);
+        self.max_density *= factor;
+        self
+    }
+
+    fn voxel(&self, x: usize, y: usize, z: usize) -> f32
+    {
+        self.values[(z * self.resolution[1] + y) * self.resolution[0] + x]
+    }
+}
+
+impl DensityField for VoxelGrid
+{
+    fn density(&self, point: &Vec3) -> f32 {
+        let extent = self.bounds.max - self.bounds.min;
+        let mut base = [0usize; 3];
+        let mut fraction = [0.0f32; 3];
+
+        for axis in 0..3 {
+            let relative = (point.e[axis] - self.bounds.min.e[axis]) / extent.e[axis];
+            if !(0.0..=1.0).contains(&relative)
+            {
+                return 0.0;
+            }
+
+            let last = self.resolution[axis] - 1;
+            let position = (relative * self.resolution[axis] as f32 - 0.5).clamp(0.0, last as f32);
+            base[axis] = (position as usize).min(last.saturating_sub(1));
+            fraction[axis] = position - base[axis] as f32;
+        }
+
+        let mut density = 0.0;
+        for corner in 0..8 {
+            let offset = [corner & 1, (corner >> 1) & 1, (corner >> 2) & 1];
+            let mut weight = 1.0;
+            let mut index = [0usize; 3];
+            for axis in 0..3 {
+                index[axis] = (base[axis] + offset[axis]).min(self.resolution[axis] - 1);
+                weight *= if offset[axis] == 1 { fraction[axis] } else { 1.0 - fraction[axis] };
+            }
+            density += weight * self.voxel(index[0], index[1], index[2]);
+        }
+        density
+    }
+
+    fn max_density(&self) -> f32 {
+        self.max_density
+    }
+}
@@ -0,0 +1,138 @@
+use rand::Rng;
+
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::material::Material;
+use crate::onb::Onb;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+
+// Tight box around a circle, padded by `Aabb::new` when the circle is axis aligned.
+pub(crate) fn circle_bbox(center: Vec3, normal: Vec3, radius: f32) -> Aabb
+{
+    let n = normal.normalize();
+    let extent = Vec3::new(
+        radius * (1.0 - n.x() * n.x()).max(0.0).sqrt(),
+        radius * (1.0 - n.y() * n.y()).max(0.0).sqrt(),
+        radius * (1.0 - n.z() * n.z()).max(0.0).sqrt(),
+    );
+    Aabb::new(center - extent, center + extent)
+}
+
+/// Flat ring between two radii around `center`, facing along `normal`. `u` runs around the ring
+/// and `v` from the inner to the outer edge.
+pub struct Annulus<T: Material>
+{
+    center: Vec3,
+    onb: Onb,
+    inner_radius: f32,
+    outer_radius: f32,
+    material: T,
+    area: f32,
+    bbox: Aabb,
+}
+
+impl<T: Material> Annulus<T>
+{
+    /// Panics unless `0 <= inner_radius < outer_radius`.
+    pub fn new(center: Vec3, normal: Vec3, inner_radius: f32, outer_radius: f32, material: T) -> Annulus<T>
+    {
+        assert!(0.0 <= inner_radius && inner_radius < outer_radius, "an annulus needs 0 <= inner radius < outer radius, got {inner_radius} and {outer_radius}");
+        let area = std::f32::consts::PI * (outer_radius * outer_radius - inner_radius * inner_radius);
+        let bbox = circle_bbox(center, normal, outer_radius);
+        Annulus { center, onb: Onb::new(&normal), inner_radius, outer_radius, material, area, bbox }
+    }
+}
+
+impl<T: Material> Hittable for Annulus<T> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        let denom = self.onb.w.dot(ray.direction);
+        if denom.abs() < 1e-8 { return None; }
+
+        let t = self.onb.w.dot(self.center - ray.origin) / denom;
+        if t < t_min || t > t_max
+        {
+            return None;
+        }
+
+        let point = ray.at(t);
+        let local = self.onb.to_local(point - self.center);
+        let radius = (local.x() * local.x() + local.y() * local.y()).sqrt();
+        if radius < self.inner_radius || radius > self.outer_radius
+        {
+            return None;
+        }
+
+        let phi = local.y().atan2(local.x());
+        let mut hit_record = HitRecord {
+            point,
+            normal: self.onb.w,
+            t,
+            material: &self.material,
+            front_face: false,
+            u: phi / std::f32::consts::TAU + 0.5,
+            v: (radius - self.inner_radius) / (self.outer_radius - self.inner_radius),
+        };
+        hit_record.set_normal(ray);
+
+        Some(hit_record)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    fn pdf(&self, origin: &Vec3, direction: &Vec3, time: f32) -> f32 {
+        match self.hit(&Ray::new(*origin, *direction, time), 0.001, f32::INFINITY) {
+            Some(hit_record) => {
+                let distance_squared = hit_record.t * hit_record.t * direction.length_squared();
+                let cosine = (direction.dot(self.onb.w) / direction.length()).abs();
+                distance_squared / (cosine * self.area)
+            }
+            None => 0.0,
+        }
+    }
+
+    // Uniform by area: the squared radius is uniform between the squared inner and outer radii.
+    fn sample(&self, origin: &Vec3, _time: f32) -> Vec3 {
+        let mut rng = rand::thread_rng();
+        let (inner, outer) = (self.inner_radius * self.inner_radius, self.outer_radius * self.outer_radius);
+        let radius = (inner + rng.gen::<f32>() * (outer - inner)).sqrt();
+        let phi = std::f32::consts::TAU * rng.gen::<f32>();
+        self.center + self.onb.transform(Vec3::new(radius * phi.cos(), radius * phi.sin(), 0.0)) - *origin
+    }
+}
+
+/// Filled circle around `center`, facing along `normal`. `u` runs around the disk and `v` from
+/// the center to the edge.
+pub struct Disk<T: Material>
+{
+    annulus: Annulus<T>,
+}
+
+impl<T: Material> Disk<T>
+{
+    /// Panics unless `radius` is positive.
+    pub fn new(center: Vec3, normal: Vec3, radius: f32, material: T) -> Disk<T>
+    {
+        Disk { annulus: Annulus::new(center, normal, 0.0, radius, material) }
+    }
+}
+
+impl<T: Material> Hittable for Disk<T> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        self.annulus.hit(ray, t_min, t_max)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.annulus.bounding_box()
+    }
+
+    fn pdf(&self, origin: &Vec3, direction: &Vec3, time: f32) -> f32 {
+        self.annulus.pdf(origin, direction, time)
+    }
+
+    fn sample(&self, origin: &Vec3, time: f32) -> Vec3 {
+        self.annulus.sample(origin, time)
+    }
+}
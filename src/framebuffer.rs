@@ -0,0 +1,49 @@
+use crate::color::Color;
+
+/// Linear, unclamped radiance values of a rendered image in row-major order, top row first.
+#[derive(Clone)]
+pub struct Framebuffer
+{
+    width: u32,
+    height: u32,
+    pixels: Vec<Color>,
+}
+
+impl Framebuffer
+{
+    pub fn new(width: u32, height: u32) -> Framebuffer
+    {
+        Framebuffer { width, height, pixels: vec![Color::new_zero(); (width * height) as usize] }
+    }
+
+    pub fn from_pixels(width: u32, height: u32, pixels: Vec<Color>) -> Framebuffer
+    {
+        assert_eq!(pixels.len(), (width * height) as usize, "pixel count does not match the dimensions");
+        Framebuffer { width, height, pixels }
+    }
+
+    pub fn width(&self) -> u32
+    {
+        self.width
+    }
+
+    pub fn height(&self) -> u32
+    {
+        self.height
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> Color
+    {
+        self.pixels[(y * self.width + x) as usize]
+    }
+
+    pub fn set_pixel(&mut self, x: u32, y: u32, color: Color)
+    {
+        self.pixels[(y * self.width + x) as usize] = color;
+    }
+
+    pub fn pixels(&self) -> &[Color]
+    {
+        &self.pixels
+    }
+}
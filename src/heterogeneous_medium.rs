@@ -0,0 +1,94 @@
+use rand::Rng;
+
+use crate::aabb::Aabb;
+use crate::constant_medium::boundary_interval;
+use crate::density::DensityField;
+use crate::hittable::{HitRecord, Hittable};
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+
+/// Participating medium whose density varies through space, such as clouds or smoke plumes,
+/// filling a convex boundary. Scattering events are found with delta tracking and shadow rays
+/// are attenuated with ratio tracking, both against the majorant of the density field.
+pub struct HeterogeneousMedium<H: Hittable, D: DensityField, M: Material>
+{
+    boundary: H,
+    density: D,
+    phase_function: M,
+}
+
+impl<H: Hittable, D: DensityField, M: Material> HeterogeneousMedium<H, D, M>
+{
+    pub fn new(boundary: H, density: D, phase_function: M) -> HeterogeneousMedium<H, D, M>
+    {
+        HeterogeneousMedium { boundary, density, phase_function }
+    }
+
+    // Tentative collisions against a homogeneous medium with the majorant density, in increasing
+    // order of `t`, each passed to `collide` with its point until it returns false.
+    fn track(&self, ray: &Ray, t_min: f32, t_max: f32, mut collide: impl FnMut(f32, f32) -> bool)
+    {
+        let Some((t_entry, t_exit)) = boundary_interval(&self.boundary, ray, t_min, t_max) else { return; };
+        let majorant = self.density.max_density();
+        if majorant <= 0.0
+        {
+            return;
+        }
+
+        let mut rng = rand::thread_rng();
+        let step = 1.0 / (majorant * ray.direction.length());
+        let mut t = t_entry;
+        loop {
+            t -= step * (1.0 - rng.gen::<f32>()).ln();
+            if t >= t_exit || !collide(t, self.density.density(&ray.at(t)) / majorant)
+            {
+                return;
+            }
+        }
+    }
+}
+
+impl<H: Hittable, D: DensityField, M: Material> Hittable for HeterogeneousMedium<H, D, M> {
+    // Delta tracking: a tentative collision is real with probability density / majorant,
+    // otherwise it is a null collision and tracking continues.
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        let mut rng = rand::thread_rng();
+        let mut hit = None;
+        self.track(ray, t_min, t_max, |t, ratio| {
+            if rng.gen::<f32>() < ratio
+            {
+                hit = Some(t);
+                return false;
+            }
+            true
+        });
+
+        let t = hit?;
+        Some(HitRecord {
+            point: ray.at(t),
+            // Volumes have no surface, so the normal and side are arbitrary.
+            normal: Vec3::new(1.0, 0.0, 0.0),
+            t,
+            material: &self.phase_function,
+            front_face: true,
+            u: 0.0,
+            v: 0.0,
+        })
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.boundary.bounding_box()
+    }
+
+    // Ratio tracking: every tentative collision scales the estimate by the chance of it being
+    // a null collision, which is less noisy than counting whether delta tracking escapes.
+    fn transmittance(&self, ray: &Ray, t_min: f32, t_max: f32) -> f32 {
+        let mut transmittance = 1.0;
+        self.track(ray, t_min, t_max, |_, ratio| {
+            transmittance *= 1.0 - ratio.min(1.0);
+            transmittance > 0.0
+        });
+        transmittance
+    }
+}
@@ -1,7 +1,13 @@
+use std::sync::Arc;
+
+use rand::Rng;
+
+use crate::aabb::Aabb;
 use crate::material::Material;
 use crate::ray::Ray;
 use crate::vec3::Vec3;
 
+/// Where and how a ray hit a surface.
 #[derive(Clone)]
 pub struct HitRecord<'a>
 {
@@ -15,6 +21,7 @@ pub struct HitRecord<'a>
 }
 
 impl HitRecord<'_> {
+    /// Flips the outward normal to face against the ray and records which side was hit.
     pub fn set_normal(&mut self, ray: &Ray)
     {
         self.front_face = ray.direction.dot(self.normal) < 0.0;
@@ -22,11 +29,110 @@ impl HitRecord<'_> {
     }
 }
 
-pub trait Hittable
+/// Stretch of a ray spent inside a closed object. `enter` is `None` when the ray already starts
+/// inside the object and `exit` is `None` when it is still inside at the end of the searched range.
+#[derive(Clone)]
+pub struct HitInterval<'a>
+{
+    pub enter: Option<HitRecord<'a>>,
+    pub exit: Option<HitRecord<'a>>,
+}
+
+// Distance moved past a hit before searching for the next one when collecting intervals.
+const INTERVAL_EPSILON: f32 = 1e-4;
+const MAX_INTERVAL_HITS: usize = 64;
+
+/// Anything a ray can intersect.
+pub trait Hittable: Sync + Send
+{
+    /// Returns the closest intersection with a ray parameter strictly inside `t_min..t_max`.
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>>;
+    /// Box enclosing the object over the whole shutter interval.
+    fn bounding_box(&self) -> Aabb;
+
+    /// Fraction of light that passes through the object along `ray` between `t_min` and `t_max`.
+    /// Surfaces are opaque, so anything hit in that range blocks the light completely.
+    fn transmittance(&self, ray: &Ray, t_min: f32, t_max: f32) -> f32
+    {
+        if self.hit(ray, t_min, t_max).is_some() { 0.0 } else { 1.0 }
+    }
+
+    /// Every stretch of `t_min..t_max` that the ray spends inside the object, in order along the
+    /// ray, which only makes sense for closed objects. By default the hits are found one after
+    /// another and paired up by whether the ray enters or leaves the object through them.
+    fn hit_intervals(&self, ray: &Ray, t_min: f32, t_max: f32) -> Vec<HitInterval<'_>>
+    {
+        let mut intervals = Vec::new();
+        let mut open: Option<HitInterval> = None;
+        let step = INTERVAL_EPSILON / ray.direction.length();
+        let mut t = t_min;
+
+        for _ in 0..MAX_INTERVAL_HITS {
+            let Some(hit_record) = self.hit(ray, t, t_max) else { break };
+            t = hit_record.t + step;
+
+            if hit_record.front_face
+            {
+                if open.is_none()
+                {
+                    open = Some(HitInterval { enter: Some(hit_record), exit: None });
+                }
+            } else if let Some(interval) = open.take()
+            {
+                intervals.push(HitInterval { enter: interval.enter, exit: Some(hit_record) });
+            } else if intervals.is_empty()
+            {
+                // Leaving before entering means the ray started inside.
+                intervals.push(HitInterval { enter: None, exit: Some(hit_record) });
+            }
+        }
+
+        intervals.extend(open);
+        intervals
+    }
+
+    /// Solid angle density with which `sample` picks `direction` from `origin`. Objects that
+    /// cannot be sampled as lights return zero.
+    fn pdf(&self, _origin: &Vec3, _direction: &Vec3, _time: f32) -> f32
+    {
+        0.0
+    }
+
+    /// Random direction from `origin` towards a point on the surface.
+    fn sample(&self, _origin: &Vec3, _time: f32) -> Vec3
+    {
+        Vec3::new(1.0, 0.0, 0.0)
+    }
+}
+
+impl<H: Hittable + ?Sized> Hittable for Arc<H>
 {
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord>;
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        (**self).hit(ray, t_min, t_max)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        (**self).bounding_box()
+    }
+
+    fn transmittance(&self, ray: &Ray, t_min: f32, t_max: f32) -> f32 {
+        (**self).transmittance(ray, t_min, t_max)
+    }
+
+    fn hit_intervals(&self, ray: &Ray, t_min: f32, t_max: f32) -> Vec<HitInterval<'_>> {
+        (**self).hit_intervals(ray, t_min, t_max)
+    }
+
+    fn pdf(&self, origin: &Vec3, direction: &Vec3, time: f32) -> f32 {
+        (**self).pdf(origin, direction, time)
+    }
+
+    fn sample(&self, origin: &Vec3, time: f32) -> Vec3 {
+        (**self).sample(origin, time)
+    }
 }
 
+/// Objects tested one after another, without any acceleration structure.
 pub struct HittableList
 {
     pub objects: Vec<Box<dyn Hittable>>,
@@ -39,12 +145,8 @@ impl HittableList {
     }
 }
 
-unsafe impl Sync for HittableList {}
-
-unsafe impl Send for HittableList {}
-
 impl Hittable for HittableList {
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
         let mut closest = t_max;
         let mut res = None;
 
@@ -59,4 +161,39 @@ impl Hittable for HittableList {
 
         res
     }
+
+    fn bounding_box(&self) -> Aabb {
+        self.objects.iter().fold(Aabb::EMPTY, |bbox, h| Aabb::surrounding(&bbox, &h.bounding_box()))
+    }
+
+    fn transmittance(&self, ray: &Ray, t_min: f32, t_max: f32) -> f32 {
+        let mut transmittance = 1.0;
+        for h in &self.objects
+        {
+            transmittance *= h.transmittance(ray, t_min, t_max);
+            if transmittance <= 0.0
+            {
+                return 0.0;
+            }
+        }
+        transmittance
+    }
+
+    // Every object is sampled with the same probability, so the density is their average.
+    fn pdf(&self, origin: &Vec3, direction: &Vec3, time: f32) -> f32 {
+        if self.objects.is_empty()
+        {
+            return 0.0;
+        }
+        self.objects.iter().map(|h| h.pdf(origin, direction, time)).sum::<f32>() / self.objects.len() as f32
+    }
+
+    fn sample(&self, origin: &Vec3, time: f32) -> Vec3 {
+        if self.objects.is_empty()
+        {
+            return Vec3::new(1.0, 0.0, 0.0);
+        }
+        let index = rand::thread_rng().gen_range(0..self.objects.len());
+        self.objects[index].sample(origin, time)
+    }
 }
\ No newline at end of file
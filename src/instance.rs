@@ -0,0 +1,288 @@
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::animation::AnimatedTransform;
+use crate::hittable::{HitInterval, HitRecord, Hittable};
+use crate::ray::Ray;
+use crate::vec3::{Mat3, Mat4, Point3, Vec3};
+
+// An invertible transform together with the matrices needed to move rays into object space and
+// hits back out of it.
+struct Placement
+{
+    transform: Mat4,
+    inverse: Mat4,
+    normal_matrix: Mat3,
+    // How much the inverse scales volumes, for converting densities over directions.
+    inverse_determinant: f32,
+}
+
+impl Placement
+{
+    fn new(transform: Mat4) -> Option<Placement>
+    {
+        Some(Placement::with_inverse(transform, transform.inverse()?))
+    }
+
+    fn with_inverse(transform: Mat4, inverse: Mat4) -> Placement
+    {
+        let inverse_determinant = inverse.linear().determinant().abs();
+        Placement { transform, inverse, normal_matrix: inverse.linear().transpose(), inverse_determinant }
+    }
+
+    // The direction is not normalized, so ray parameters are the same in both spaces.
+    fn to_object(&self, ray: &Ray) -> Ray
+    {
+        Ray::new(self.inverse.transform_point(Point3(ray.origin)).0, self.inverse.transform_vector(ray.direction), ray.time)
+    }
+
+    fn to_world<'a>(&self, mut hit_record: HitRecord<'a>) -> HitRecord<'a>
+    {
+        hit_record.point = self.transform.transform_point(Point3(hit_record.point)).0;
+        hit_record.normal = (self.normal_matrix * hit_record.normal).normalize();
+        hit_record
+    }
+
+    fn hit<'a>(&self, object: &'a dyn Hittable, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'a>>
+    {
+        object.hit(&self.to_object(ray), t_min, t_max).map(|hit_record| self.to_world(hit_record))
+    }
+
+    fn hit_intervals<'a>(&self, object: &'a dyn Hittable, ray: &Ray, t_min: f32, t_max: f32) -> Vec<HitInterval<'a>>
+    {
+        let mut intervals = object.hit_intervals(&self.to_object(ray), t_min, t_max);
+        for interval in &mut intervals {
+            interval.enter = interval.enter.take().map(|hit_record| self.to_world(hit_record));
+            interval.exit = interval.exit.take().map(|hit_record| self.to_world(hit_record));
+        }
+        intervals
+    }
+
+    // A linear map A takes a unit direction d to A d / |A d|, which stretches solid angles by
+    // |det A| / |A d|³. Only rotations and uniform scaling leave them unchanged.
+    fn pdf(&self, object: &dyn Hittable, origin: &Vec3, direction: &Vec3, time: f32) -> f32
+    {
+        let local = self.inverse.transform_vector(*direction);
+        let stretch = local.length() / direction.length();
+        object.pdf(&self.inverse.transform_point(Point3(*origin)).0, &local, time) * self.inverse_determinant / (stretch * stretch * stretch)
+    }
+
+    fn sample(&self, object: &dyn Hittable, origin: &Vec3, time: f32) -> Vec3
+    {
+        self.transform.transform_vector(object.sample(&self.inverse.transform_point(Point3(*origin)).0, time))
+    }
+}
+
+/// Places a shared object in the world with an affine transform, so the same geometry can be
+/// translated, rotated and scaled without being rebuilt. Rays are moved into object space
+/// instead of transforming the object.
+pub struct Instance
+{
+    object: Arc<dyn Hittable>,
+    placement: Placement,
+    bbox: Aabb,
+}
+
+impl Instance
+{
+    /// Returns `None` if `transform` cannot be inverted, for example a scale of zero along some
+    /// axis.
+    pub fn new(object: Arc<dyn Hittable>, transform: Mat4) -> Option<Instance>
+    {
+        let placement = Placement::new(transform)?;
+        let bbox = transform_bbox(&object.bounding_box(), &transform);
+        Some(Instance { object, placement, bbox })
+    }
+}
+
+fn transform_bbox(bbox: &Aabb, transform: &Mat4) -> Aabb
+{
+    if bbox.min.x() > bbox.max.x()
+    {
+        return Aabb::EMPTY;
+    }
+    if bbox.is_unbounded()
+    {
+        return Aabb::UNIVERSE;
+    }
+
+    let mut result = Aabb::EMPTY;
+    for corner in 0..8 {
+        let point = Vec3::new(
+            if corner & 1 == 0 { bbox.min.x() } else { bbox.max.x() },
+            if corner & 2 == 0 { bbox.min.y() } else { bbox.max.y() },
+            if corner & 4 == 0 { bbox.min.z() } else { bbox.max.z() },
+        );
+        let point = transform.transform_point(Point3(point)).0;
+        result = Aabb::surrounding(&result, &Aabb::new(point, point));
+    }
+    result
+}
+
+impl Hittable for Instance {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        self.placement.hit(&*self.object, ray, t_min, t_max)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    fn transmittance(&self, ray: &Ray, t_min: f32, t_max: f32) -> f32 {
+        self.object.transmittance(&self.placement.to_object(ray), t_min, t_max)
+    }
+
+    fn hit_intervals(&self, ray: &Ray, t_min: f32, t_max: f32) -> Vec<HitInterval<'_>> {
+        self.placement.hit_intervals(&*self.object, ray, t_min, t_max)
+    }
+
+    fn pdf(&self, origin: &Vec3, direction: &Vec3, time: f32) -> f32 {
+        self.placement.pdf(&*self.object, origin, direction, time)
+    }
+
+    fn sample(&self, origin: &Vec3, time: f32) -> Vec3 {
+        self.placement.sample(&*self.object, origin, time)
+    }
+}
+
+// Samples per pair of keyframes when bounding the motion. Interpolated rotations can bulge out
+// slightly between samples, which the padding below makes up for.
+const MOTION_BOUND_SAMPLES: usize = 32;
+const MOTION_BOUND_PADDING: f32 = 0.01;
+
+/// Like [`Instance`], with the transform following keyframes over time so the object can move,
+/// turn and grow during the shutter interval. The transform is evaluated at each ray's time.
+pub struct AnimatedInstance
+{
+    object: Arc<dyn Hittable>,
+    animation: AnimatedTransform,
+    bbox: Aabb,
+}
+
+impl AnimatedInstance
+{
+    pub fn new(object: Arc<dyn Hittable>, animation: AnimatedTransform) -> AnimatedInstance
+    {
+        let bbox = object.bounding_box();
+        let keyframes = animation.keyframes();
+
+        let mut motion = transform_bbox(&bbox, &keyframes[0].matrix());
+        for pair in keyframes.windows(2) {
+            for i in 1..=MOTION_BOUND_SAMPLES {
+                let time = pair[0].time + (pair[1].time - pair[0].time) * i as f32 / MOTION_BOUND_SAMPLES as f32;
+                motion = Aabb::surrounding(&motion, &transform_bbox(&bbox, &animation.matrix(time)));
+            }
+        }
+
+        if !motion.is_unbounded() && motion.min.x() <= motion.max.x()
+        {
+            let pad = MOTION_BOUND_PADDING * (motion.max - motion.min).length();
+            let pad = Vec3::new(pad, pad, pad);
+            motion = Aabb::new(motion.min - pad, motion.max + pad);
+        }
+
+        AnimatedInstance { object, animation, bbox: motion }
+    }
+
+    // Every ray has its own time, so the placement is rebuilt for each query rather than
+    // cached. Its inverse comes from the interpolated parts, which avoids a general 4x4
+    // inversion; what remains is a slerp and a few 3x3 products.
+    fn placement(&self, time: f32) -> Option<Placement>
+    {
+        let keyframe = self.animation.at(time);
+        Some(Placement::with_inverse(keyframe.matrix(), keyframe.inverse_matrix()?))
+    }
+}
+
+impl Hittable for AnimatedInstance {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        self.placement(ray.time)?.hit(&*self.object, ray, t_min, t_max)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    fn transmittance(&self, ray: &Ray, t_min: f32, t_max: f32) -> f32 {
+        self.placement(ray.time).map_or(1.0, |placement| self.object.transmittance(&placement.to_object(ray), t_min, t_max))
+    }
+
+    fn hit_intervals(&self, ray: &Ray, t_min: f32, t_max: f32) -> Vec<HitInterval<'_>> {
+        self.placement(ray.time).map_or(Vec::new(), |placement| placement.hit_intervals(&*self.object, ray, t_min, t_max))
+    }
+
+    fn pdf(&self, origin: &Vec3, direction: &Vec3, time: f32) -> f32 {
+        self.placement(time).map_or(0.0, |placement| placement.pdf(&*self.object, origin, direction, time))
+    }
+
+    fn sample(&self, origin: &Vec3, time: f32) -> Vec3 {
+        self.placement(time).map_or(Vec3::new(1.0, 0.0, 0.0), |placement| placement.sample(&*self.object, origin, time))
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+    use crate::material::DiffuseMaterial;
+    use crate::quad::Quad;
+    use crate::sphere::Sphere;
+    use crate::texture::SolidColor;
+
+    fn light() -> DiffuseMaterial<SolidColor>
+    {
+        DiffuseMaterial::new(SolidColor::new(1.0, 1.0, 1.0))
+    }
+
+    // Monte Carlo estimate of the integral of the density over all directions, which is one
+    // wherever the object can be seen.
+    fn total_probability(object: &dyn Hittable, origin: Vec3) -> f32
+    {
+        let mut rng = StdRng::seed_from_u64(3);
+        let samples = 400_000;
+        let sum: f32 = (0..samples).map(|_| {
+            let z: f32 = rng.gen_range(-1.0..1.0);
+            let phi = std::f32::consts::TAU * rng.gen::<f32>();
+            let r = (1.0 - z * z).sqrt();
+            object.pdf(&origin, &Vec3::new(r * phi.cos(), r * phi.sin(), z), 0.0)
+        }).sum();
+        4.0 * std::f32::consts::PI * sum / samples as f32
+    }
+
+    #[test]
+    fn light_density_stays_normalized_under_non_uniform_scaling()
+    {
+        let transform = Mat4::translation(Vec3::new(0.5, 0.0, -3.0)) * Mat4::rotation_y(30.0) * Mat4::scaling(Vec3::new(3.0, 0.5, 1.0));
+        let sphere = Instance::new(Arc::new(Sphere::new(Vec3::new_zero(), 1.0, light())), transform).unwrap();
+        let quad = Quad::new(Vec3::new(-1.0, -1.0, 0.0), Vec3::new(2.0, 0.0, 0.0), Vec3::new(0.0, 2.0, 0.0), light());
+        let quad = Instance::new(Arc::new(quad), transform).unwrap();
+
+        for object in [&sphere, &quad] {
+            let total = total_probability(object, Vec3::new(0.0, 0.2, 1.0));
+            assert!((total - 1.0).abs() < 0.02, "{total}");
+        }
+    }
+
+    #[test]
+    fn sampled_directions_hit_the_instance()
+    {
+        let transform = Mat4::translation(Vec3::new(0.0, 0.0, -4.0)) * Mat4::scaling(Vec3::new(2.0, 0.25, 1.0));
+        let sphere = Instance::new(Arc::new(Sphere::new(Vec3::new_zero(), 1.0, light())), transform).unwrap();
+        let origin = Vec3::new(1.0, 1.0, 0.0);
+
+        for _ in 0..1000 {
+            let direction = sphere.sample(&origin, 0.0);
+            assert!(sphere.pdf(&origin, &direction, 0.0) > 0.0);
+        }
+    }
+
+    #[test]
+    fn singular_transforms_are_rejected()
+    {
+        let sphere: Arc<dyn Hittable> = Arc::new(Sphere::new(Vec3::new_zero(), 1.0, light()));
+        assert!(Instance::new(sphere, Mat4::scaling(Vec3::new(1.0, 0.0, 1.0))).is_none());
+    }
+}
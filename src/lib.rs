@@ -0,0 +1,76 @@
+//! A multithreaded path tracer.
+//!
+//! A scene is a [`Camera`](camera::Camera), a [`HittableList`](hittable::HittableList) of
+//! objects and a second list of the emitters among them that should be sampled directly, either
+//! built in code or loaded from a JSON file with [`Scene`](scene::Scene).
+//! [`Camera::render`](camera::Camera::render) produces a [`Framebuffer`](framebuffer::Framebuffer)
+//! of linear colors that can be inspected directly or written to disk with
+//! [`write_image`](output::write_image).
+//!
+//! ```no_run
+//! use std::path::Path;
+//!
+//! use raytracer::bvh::FlatBvh;
+//! use raytracer::camera::Camera;
+//! use raytracer::hittable::HittableList;
+//! use raytracer::material::Lambertian;
+//! use raytracer::output::{write_image, OutputFormat};
+//! use raytracer::sphere::Sphere;
+//! use raytracer::texture::SolidColor;
+//! use raytracer::vec3::{Mat4, Point3, Vec3};
+//!
+//! let mut camera = Camera::new(
+//!     10.0,
+//!     0.0,
+//!     20.0,
+//!     &Mat4::look_at(Point3::new(13.0, 2.0, 3.0), Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0)),
+//!     16.0 / 9.0,
+//!     400,
+//! );
+//! camera.samples_per_pixel = 50;
+//!
+//! let mut world = HittableList::new(vec![]);
+//! world.objects.push(Box::new(Sphere::new(
+//!     Vec3::new(0.0, 1.0, 0.0),
+//!     1.0,
+//!     Lambertian::new(SolidColor::new(0.4, 0.2, 0.1)),
+//! )));
+//!
+//! let lights = HittableList::new(vec![]);
+//! let framebuffer = camera.render(&FlatBvh::new(world), &lights, false);
+//! println!("{}", framebuffer.pixel(200, 112));
+//! write_image(Path::new("sphere.png"), OutputFormat::Png, &framebuffer).unwrap();
+//! ```
+
+pub mod aabb;
+pub mod animation;
+pub mod box_shape;
+pub mod bvh;
+pub mod camera;
+pub mod color;
+pub mod cone;
+pub mod constant_medium;
+pub mod csg;
+pub mod cylinder;
+pub mod density;
+pub mod disk;
+pub mod framebuffer;
+pub mod heterogeneous_medium;
+pub mod hittable;
+pub mod instance;
+pub mod material;
+pub mod mesh;
+pub mod onb;
+pub mod output;
+pub mod perlin;
+pub mod plane;
+pub mod polynomial;
+pub mod quad;
+pub mod ray;
+pub mod scene;
+pub mod sdf;
+pub mod sphere;
+pub mod texture;
+pub mod torus;
+pub mod triangle;
+pub mod vec3;
@@ -1,402 +1,262 @@
-mod camera;
-mod color;
-mod hittable;
-mod material;
-mod perlin;
-mod quad;
-mod ray;
-mod sphere;
-mod texture;
-mod vec3;
-
-use crate::camera::Camera;
-use crate::color::Color;
-use crate::hittable::*;
-use crate::material::*;
-use crate::quad::Quad;
-use crate::sphere::Sphere;
-use crate::texture::{CheckerTexture, ImageTexture, NoiseTexture, SolidColor};
-use crate::vec3::Vec3;
-use rand::Rng;
+use raytracer::bvh::{BvhNode, FlatBvh};
+use raytracer::camera::Camera;
+use raytracer::hittable::{Hittable, HittableList};
+use raytracer::output::{write_image, OutputFormat};
+use raytracer::scene::Scene;
 use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+const SCENES: [(&str, &str); 17] = [
+    ("Bouncing Spheres", include_str!("../scenes/bouncing_spheres.json")),
+    ("Checkered Spheres", include_str!("../scenes/checkered_spheres.json")),
+    ("Earth (Please supply a texture with name earthmap.jpg)", include_str!("../scenes/earth.json")),
+    ("Perlin spheres", include_str!("../scenes/perlin_spheres.json")),
+    ("Quadrilaterals", include_str!("../scenes/quads.json")),
+    ("Ligt with Perlin Spheres", include_str!("../scenes/simple_light.json")),
+    ("Cornell Box", include_str!("../scenes/cornell_box.json")),
+    ("Triangles", include_str!("../scenes/triangles.json")),
+    ("Mesh (Please supply a model with name model.obj)", include_str!("../scenes/mesh.json")),
+    ("Cornell Smoke", include_str!("../scenes/cornell_smoke.json")),
+    ("Cornell Cloud", include_str!("../scenes/cornell_cloud.json")),
+    ("Cylinders, Cones, Disks, Annuli and Tori", include_str!("../scenes/primitives.json")),
+    ("Signed Distance Fields", include_str!("../scenes/sdf.json")),
+    ("Constructive Solid Geometry", include_str!("../scenes/csg.json")),
+    ("Keyframed Motion Blur", include_str!("../scenes/motion_blur.json")),
+    ("Rolling Shutter", include_str!("../scenes/rolling_shutter.json")),
+    ("Animation (render with frames=1-48)", include_str!("../scenes/animation.json")),
+];
+
+enum Accelerator {
+    Linear,
+    Median,
+    Sah,
+}
+
+struct Options {
+    disk_sampling: bool,
+    accelerator: Accelerator,
+    output: Option<String>,
+    high_bit_depth: bool,
+    /// First and last frame of an animation, inclusive.
+    frames: Option<(u32, u32)>,
+}
 
 fn main() {
     let args: Vec<String> = env::args().collect();
-    let mut disk_sampling = false;
-
-    if args.len() == 3 && args[2] == *"disk_sampling" {
-        disk_sampling = true;
-    } else if args.len() == 1 {
-        println!("Example Usage: raytracer 1 disk-sampling.\nWhere 1 is the scene number and disk-sampling is an optional argument.\nTo see all avaliable scenes types: raytracer list");
+    let mut options = Options {
+        disk_sampling: false,
+        accelerator: Accelerator::Sah,
+        output: None,
+        high_bit_depth: false,
+        frames: None,
+    };
+
+    if args.len() == 1 {
+        println!("Example Usage: raytracer 1 disk-sampling.\nWhere 1 is the scene number or the path of a scene file and disk-sampling is an optional argument.\nThe acceleration structure can be chosen with linear, median or sah (default).\nThe image is written to final_image.ppm unless another .png, .jpg, .ppm, .exr or .hdr path is given, add 16bit for 16-bit PNGs.\nAdd frames=1-48 to render an animation into frame_0001.png and so on, skipping frames that already exist.\nTo see all avaliable scenes types: raytracer list");
         return;
     }
 
-    match args[1].as_str() {
-        "list" => {
-            println!("1: Bouncing Spheres");
-            println!("2: Checkered Spheres");
-            println!("3: Earth (Please supply a texture with name earthmap.jpg)");
-            println!("4: Perlin spheres");
-            println!("5: Quadrilaterals");
-            println!("6: Ligt with Perlin Spheres");
-            println!("7: Cornell Box");
+    for arg in &args[2..] {
+        match arg.as_str() {
+            "disk_sampling" => options.disk_sampling = true,
+            "linear" => options.accelerator = Accelerator::Linear,
+            "median" => options.accelerator = Accelerator::Median,
+            "sah" => options.accelerator = Accelerator::Sah,
+            "16bit" => options.high_bit_depth = true,
+            path if OutputFormat::from_path(Path::new(path), false).is_some() => {
+                options.output = Some(path.to_string())
+            }
+            range if range.starts_with("frames=") => {
+                match parse_frame_range(range.trim_start_matches("frames=")) {
+                    Some(frames) => options.frames = Some(frames),
+                    None => println!("Ignoring invalid frame range {arg}"),
+                }
+            }
+            _ => println!("Ignoring unknown argument {arg}"),
         }
-        "1" => bouncing_spheres(disk_sampling),
-        "2" => checkered_spheres(disk_sampling),
-        "3" => earth(disk_sampling),
-        "4" => perlin_spheres(disk_sampling),
-        "5" => quads(disk_sampling),
-        "6" => simple_light(disk_sampling),
-        "7" => cornell_box(disk_sampling),
-        _ => println!("To see all avaliable scenes: raytracer list"),
     }
-}
 
-fn cornell_box(disk_sampling: bool) {
-    let mut camera: Camera = Camera::new(
-        10.0,
-        0.0,
-        40.0,
-        Vec3::new(278.0, 278.0, -800.0),
-        Vec3::new(278.0, 278.0, 0.0),
-        Vec3::new(0.0, 1.0, 0.0),
-        1.0,
-        800,
-        1000,
-        50,
-    );
-    camera.background = Color::new_zero();
-
-    let mut world = HittableList::new(vec![]);
-
-    let red = Lambertian::new(SolidColor::new(0.65, 0.05, 0.05));
-    let white = Lambertian::new(SolidColor::new(0.73, 0.73, 0.73));
-    let green = Lambertian::new(SolidColor::new(0.12, 0.45, 0.15));
-    let light = DiffuseMaterial::new(SolidColor::new(15.0, 15.0, 15.0));
+    if args[1] == "list" {
+        for (i, (name, _)) in SCENES.iter().enumerate() {
+            println!("{}: {name}", i + 1);
+        }
+        return;
+    }
 
-    world.objects.push(Box::new(Quad::new(
-        Vec3::new(555.0, 0.0, 0.0),
-        Vec3::new(0.0, 555.0, 0.0),
-        Vec3::new(0.0, 0.0, 555.0),
-        green,
-    )));
-    world.objects.push(Box::new(Quad::new(
-        Vec3::new(0.0, 0.0, 0.0),
-        Vec3::new(0.0, 555.0, 0.0),
-        Vec3::new(0.0, 0.0, 555.0),
-        red,
-    )));
-    world.objects.push(Box::new(Quad::new(
-        Vec3::new(343.0, 554.0, 332.0),
-        Vec3::new(-130.0, 0.0, 0.0),
-        Vec3::new(0.0, 0.0, -105.0),
-        light,
-    )));
-    world.objects.push(Box::new(Quad::new(
-        Vec3::new(0.0, 0.0, 0.0),
-        Vec3::new(555.0, 0.0, 0.0),
-        Vec3::new(0.0, 0.0, 555.0),
-        white.clone(),
-    )));
-    world.objects.push(Box::new(Quad::new(
-        Vec3::new(555.0, 555.0, 555.0),
-        Vec3::new(-555.0, 0.0, 0.0),
-        Vec3::new(0.0, 0.0, -555.0),
-        white.clone(),
-    )));
-    world.objects.push(Box::new(Quad::new(
-        Vec3::new(0.0, 0.0, 555.0),
-        Vec3::new(555.0, 0.0, 0.0),
-        Vec3::new(0.0, 555.0, 0.0),
-        white,
-    )));
+    let scene = match args[1].parse::<usize>() {
+        Ok(number) if (1..=SCENES.len()).contains(&number) => Scene::parse(SCENES[number - 1].1, Path::new("")),
+        Ok(_) => {
+            println!("To see all avaliable scenes: raytracer list");
+            return;
+        }
+        Err(_) => Scene::load(&args[1]),
+    };
 
-    camera.render(&world, disk_sampling);
+    match scene {
+        Ok(scene) => render(scene, &options),
+        Err(error) => println!("Could not load scene {}: {error}", args[1]),
+    }
 }
 
-fn simple_light(disk_sampling: bool) {
-    let mut camera: Camera = Camera::new(
-        10.0,
-        0.0,
-        20.0,
-        Vec3::new(26.0, 3.0, 6.0),
-        Vec3::new(0.0, 2.0, 0.0),
-        Vec3::new(0.0, 1.0, 0.0),
-        16.0 / 9.0,
-        800,
-        10000,
-        50,
-    );
-    camera.background = Color::new_zero();
-
-    let mut world = HittableList::new(vec![]);
-    let perlin_texture = NoiseTexture::new(4.0);
-    let diffuse_light = DiffuseMaterial::new(SolidColor::new(4.0, 4.0, 4.0));
-
-    world.objects.push(Box::new(Sphere::new(
-        Vec3::new(0.0, -1000.0, 0.0),
-        1000.0,
-        Lambertian::new(perlin_texture.clone()),
-    )));
-    world.objects.push(Box::new(Sphere::new(
-        Vec3::new(0.0, 2.0, 0.0),
-        2.0,
-        Lambertian::new(perlin_texture),
-    )));
-    world.objects.push(Box::new(Quad::new(
-        Vec3::new(3.0, 1.0, -2.0),
-        Vec3::new(2.0, 0.0, 0.0),
-        Vec3::new(0.0, 2.0, 0.0),
-        diffuse_light.clone(),
-    )));
-    world.objects.push(Box::new(Sphere::new(
-        Vec3::new(0.0, 7.0, 0.0),
-        2.0,
-        diffuse_light,
-    )));
-
-    camera.render(&world, disk_sampling);
+fn parse_frame_range(range: &str) -> Option<(u32, u32)> {
+    let (first, last) = range.split_once('-')?;
+    let (first, last) = (first.parse().ok()?, last.parse().ok()?);
+    (1 <= first && first <= last).then_some((first, last))
 }
 
-fn quads(disk_sampling: bool) {
-    let camera: Camera = Camera::new(
-        10.0,
-        0.0,
-        80.0,
-        Vec3::new(0.0, 0.0, 9.0),
-        Vec3::new(0.0, 0.0, 0.0),
-        Vec3::new(0.0, 1.0, 0.0),
-        1.0,
-        800,
-        100,
-        50,
-    );
-    let mut world = HittableList::new(vec![]);
+fn render(mut scene: Scene, options: &Options) {
+    // The scene is still needed for the cameras of later frames, so only its objects move out.
+    let objects = std::mem::replace(&mut scene.world, HittableList::new(vec![]));
+    let world: Box<dyn Hittable> = match options.accelerator {
+        Accelerator::Linear => Box::new(objects),
+        Accelerator::Median => Box::new(BvhNode::new(objects)),
+        Accelerator::Sah => {
+            let bvh = FlatBvh::new(objects);
+            println!("{}", bvh.stats());
+            Box::new(bvh)
+        }
+    };
+
+    let Some((first, last)) = options.frames else {
+        let output = options.output.as_deref().unwrap_or("final_image.ppm");
+        render_frame(
+            &scene.camera,
+            &*world,
+            &scene.lights,
+            Path::new(output),
+            options,
+        );
+        return;
+    };
 
-    let left_red = Lambertian::new(SolidColor::new(1.0, 0.2, 0.2));
-    let back_green = Lambertian::new(SolidColor::new(0.2, 1.0, 0.2));
-    let right_blue = Lambertian::new(SolidColor::new(0.2, 0.2, 1.0));
-    let upper_orange = Lambertian::new(SolidColor::new(1.0, 0.5, 0.0));
-    let lower_teal = Lambertian::new(SolidColor::new(0.2, 0.8, 0.8));
+    let names = FrameNames::new(options.output.as_deref());
+    let frames = missing_frames(first, last, &names);
+    if frames.is_empty() {
+        println!("All frames from {first} to {last} have already been rendered");
+        return;
+    }
+    let skipped = (last - first + 1) as usize - frames.len();
+    if skipped > 0 {
+        println!("Skipping {skipped} frames that have already been rendered");
+    }
 
-    world.objects.push(Box::new(Quad::new(
-        Vec3::new(-3.0, -2.0, 5.0),
-        Vec3::new(0.0, 0.0, -4.0),
-        Vec3::new(0.0, 4.0, 0.0),
-        left_red,
-    )));
-    world.objects.push(Box::new(Quad::new(
-        Vec3::new(-2.0, -2.0, 0.0),
-        Vec3::new(4.0, 0.0, 0.0),
-        Vec3::new(0.0, 4.0, 0.0),
-        back_green,
-    )));
-    world.objects.push(Box::new(Quad::new(
-        Vec3::new(3.0, -2.0, 1.0),
-        Vec3::new(0.0, 0.0, 4.0),
-        Vec3::new(0.0, 4.0, 0.0),
-        right_blue,
-    )));
-    world.objects.push(Box::new(Quad::new(
-        Vec3::new(-2.0, 3.0, 1.0),
-        Vec3::new(4.0, 0.0, 0.0),
-        Vec3::new(0.0, 0.0, 4.0),
-        upper_orange,
-    )));
-    world.objects.push(Box::new(Quad::new(
-        Vec3::new(-2.0, -3.0, 5.0),
-        Vec3::new(4.0, 0.0, 0.0),
-        Vec3::new(0.0, 0.0, -4.0),
-        lower_teal,
-    )));
+    for frame in frames {
+        println!("Rendering frame {frame} of {last}");
+        let camera = scene.frame_camera(frame);
+        let path = names.path(frame);
 
-    camera.render(&world, disk_sampling);
+        // Rendered under another name first, so an interrupted render never leaves a partly
+        // written frame behind that resuming would mistake for a finished one.
+        let partial = names.partial_path(frame);
+        if !render_frame(&camera, &*world, &scene.lights, &partial, options) {
+            return;
+        }
+        if let Err(error) = fs::rename(&partial, &path) {
+            println!("Could not write {}: {error}", path.display());
+            return;
+        }
+    }
 }
 
-fn perlin_spheres(disk_sampling: bool) {
-    let camera: Camera = Camera::new(
-        10.0,
-        0.0,
-        20.0,
-        Vec3::new(13.0, 2.0, 3.0),
-        Vec3::new(0.0, 0.0, 0.0),
-        Vec3::new(0.0, 1.0, 0.0),
-        16.0 / 9.0,
-        800,
-        100,
-        50,
-    );
-    let mut world = HittableList::new(vec![]);
-
-    let perlin_texture = NoiseTexture::new(4.0);
-    let surface1 = Lambertian::new(perlin_texture.clone());
-    let surface2 = Lambertian::new(perlin_texture);
-
-    world.objects.push(Box::new(Sphere::new(
-        Vec3::new(0.0, -1000.0, 0.0),
-        1000.0,
-        surface1,
-    )));
-    world.objects.push(Box::new(Sphere::new(
-        Vec3::new(0.0, 2.0, 0.0),
-        2.0,
-        surface2,
-    )));
-    camera.render(&world, disk_sampling);
+// Names of the frames of an animation. Frames go next to the output path if one was given,
+// taking its format but not its name.
+struct FrameNames {
+    directory: PathBuf,
+    extension: String,
 }
 
-fn earth(disk_sampling: bool) {
-    let camera: Camera = Camera::new(
-        10.0,
-        0.0,
-        20.0,
-        Vec3::new(0.0, 0.0, 12.0),
-        Vec3::new(0.0, 0.0, 0.0),
-        Vec3::new(0.0, 1.0, 0.0),
-        16.0 / 9.0,
-        800,
-        100,
-        50,
-    );
-    let mut world = HittableList::new(vec![]);
+impl FrameNames {
+    fn new(output: Option<&str>) -> FrameNames {
+        let output = Path::new(output.unwrap_or("frame.png"));
+        FrameNames {
+            directory: output.parent().unwrap_or(Path::new("")).to_path_buf(),
+            extension: output
+                .extension()
+                .and_then(|e| e.to_str())
+                .unwrap_or("png")
+                .to_string(),
+        }
+    }
 
-    let earth_texture = ImageTexture::new("earthmap.jpg");
-    let earth_surface = Lambertian::new(earth_texture);
+    fn path(&self, frame: u32) -> PathBuf {
+        self.directory
+            .join(format!("frame_{frame:04}.{}", self.extension))
+    }
 
-    world
-        .objects
-        .push(Box::new(Sphere::new(Vec3::new_zero(), 2.0, earth_surface)));
-    camera.render(&world, disk_sampling);
+    fn partial_path(&self, frame: u32) -> PathBuf {
+        self.directory
+            .join(format!("frame_{frame:04}.partial.{}", self.extension))
+    }
 }
 
-fn checkered_spheres(disk_sampling: bool) {
-    let camera: Camera = Camera::new(
-        10.0,
-        0.0,
-        20.0,
-        Vec3::new(13.0, 2.0, 3.0),
-        Vec3::new(0.0, 0.0, 0.0),
-        Vec3::new(0.0, 1.0, 0.0),
-        16.0 / 9.0,
-        800,
-        100,
-        50,
-    );
-    let mut world = HittableList::new(vec![]);
-
-    let checker = CheckerTexture::new(
-        75.0,
-        100.0,
-        SolidColor::new(0.2, 0.3, 0.1),
-        SolidColor::new(0.9, 0.9, 0.9),
-    );
-    world.objects.push(Box::new(Sphere::new(
-        Vec3::new(0.0, -10.0, 0.0),
-        10.0,
-        Lambertian::new(checker),
-    )));
-    world.objects.push(Box::new(Sphere::new(
-        Vec3::new(0.0, 10.0, 0.0),
-        10.0,
-        Lambertian::new(checker),
-    )));
-
-    camera.render(&world, disk_sampling);
+// Frames from `first` to `last` that have not been written yet. A leftover partial file does
+// not count, since its render was interrupted.
+fn missing_frames(first: u32, last: u32, names: &FrameNames) -> Vec<u32> {
+    (first..=last)
+        .filter(|&frame| !names.path(frame).exists())
+        .collect()
 }
 
-fn bouncing_spheres(disk_sampling: bool) {
-    let camera: Camera = Camera::new(
-        10.0,
-        0.6,
-        20.0,
-        Vec3::new(13.0, 2.0, 3.0),
-        Vec3::new(0.0, 0.0, 0.0),
-        Vec3::new(0.0, 1.0, 0.0),
-        16.0 / 9.0,
-        800,
-        100,
-        50,
-    );
-    let world = generate_random_scene();
-    camera.render(&world, disk_sampling);
+// Returns whether the image could be written.
+fn render_frame(
+    camera: &Camera,
+    world: &dyn Hittable,
+    lights: &HittableList,
+    output: &Path,
+    options: &Options,
+) -> bool {
+    let format = OutputFormat::from_path(output, options.high_bit_depth).unwrap();
+    let start = Instant::now();
+    let framebuffer = camera.render(world, lights, options.disk_sampling);
+    println!("Done in {:?}", start.elapsed());
+
+    if let Err(error) = write_image(output, format, &framebuffer) {
+        println!("Could not write {}: {error}", output.display());
+        return false;
+    }
+    true
 }
 
-fn generate_random_scene() -> HittableList {
-    let mut world = HittableList::new(vec![]);
-    let checker = CheckerTexture::new(
-        1.0,
-        0.5,
-        SolidColor::new(0.2, 0.3, 0.1),
-        SolidColor::new(0.9, 0.9, 0.9),
-    );
-    let ground_material = Lambertian::new(checker);
-
-    world.objects.push(Box::new(Sphere::new(
-        Vec3::new(0.0, -1000.0, 0.0),
-        1000.0,
-        ground_material,
-    )));
-    let mut rng = rand::thread_rng();
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    for a in -11..11 {
-        for b in -11..11 {
-            let rnd = rng.gen::<f32>();
-            let center = Vec3::new(
-                a as f32 + 0.9 * rng.gen::<f32>(),
-                0.2,
-                b as f32 + 0.9 * rng.gen::<f32>(),
-            );
-
-            if (center - Vec3::new(4.0, 0.2, 0.0)).length() > 0.9 {
-                if rnd < 0.8 {
-                    let color = Color::new(rng.gen::<f32>(), rng.gen::<f32>(), rng.gen::<f32>())
-                        * Color::new(rng.gen::<f32>(), rng.gen::<f32>(), rng.gen::<f32>());
-                    let albedo = SolidColor::new(color.x(), color.y(), color.z());
-                    let sphere_material = Lambertian::new(albedo);
-                    world
-                        .objects
-                        .push(Box::new(Sphere::new(center, 0.2, sphere_material)));
-                } else if rnd < 0.95 {
-                    let albedo = Color::new(
-                        rng.gen_range(0.5..1.0),
-                        rng.gen_range(0.5..1.0),
-                        rng.gen_range(0.5..1.0),
-                    );
-                    let fuzz = rng.gen_range(0.0..0.5);
-                    let sphere_material = Metal::new(albedo, fuzz);
-                    world
-                        .objects
-                        .push(Box::new(Sphere::new(center, 0.2, sphere_material)))
-                } else {
-                    let sphere_material = Dielectric::new(1.5);
-                    world
-                        .objects
-                        .push(Box::new(Sphere::new(center, 0.2, sphere_material)))
-                }
-            }
-        }
+    // A fresh directory for the frames of one test.
+    fn frame_directory(name: &str) -> PathBuf {
+        let directory = env::temp_dir().join(format!("raytracer-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+        directory
     }
 
-    let material1 = Dielectric::new(1.5);
-    world.objects.push(Box::new(Sphere::new(
-        Vec3::new(0.0, 1.0, 0.0),
-        1.0,
-        material1,
-    )));
-
-    let material2 = Lambertian::new(SolidColor::new(0.4, 0.2, 0.1));
-    world.objects.push(Box::new(Sphere::new(
-        Vec3::new(-4.0, 1.0, 0.0),
-        1.0,
-        material2,
-    )));
+    #[test]
+    fn frames_already_written_are_skipped() {
+        let directory = frame_directory("skip");
+        let names = FrameNames::new(directory.join("out.exr").to_str());
+        for frame in [1, 2, 4] {
+            fs::write(names.path(frame), "").unwrap();
+        }
 
-    let material3 = Metal::new(Color::new(0.7, 0.6, 0.5), 0.0);
-    world.objects.push(Box::new(Sphere::new(
-        Vec3::new(4.0, 1.0, 0.0),
-        1.0,
-        material3,
-    )));
+        assert_eq!(names.path(3), directory.join("frame_0003.exr"));
+        assert_eq!(missing_frames(1, 5, &names), vec![3, 5]);
+        assert!(missing_frames(1, 2, &names).is_empty());
+        fs::remove_dir_all(directory).unwrap();
+    }
 
-    world
+    #[test]
+    fn interrupted_frames_are_rendered_again() {
+        let directory = frame_directory("partial");
+        let names = FrameNames::new(directory.join("out.png").to_str());
+        fs::write(names.path(1), "").unwrap();
+        fs::write(names.partial_path(2), "").unwrap();
+
+        assert_eq!(
+            names.partial_path(2),
+            directory.join("frame_0002.partial.png")
+        );
+        assert_eq!(missing_frames(1, 3, &names), vec![2, 3]);
+        fs::remove_dir_all(directory).unwrap();
+    }
 }
@@ -1,15 +1,64 @@
+use std::sync::Arc;
+
 use rand::Rng;
 use crate::color::Color;
 use crate::hittable::HitRecord;
+use crate::onb::Onb;
 use crate::ray::Ray;
 use crate::texture::Texture;
 use crate::vec3::Vec3;
 
 
-pub trait Material
+/// Describes how light interacts with a surface.
+pub trait Material: Sync + Send
 {
+    /// Returns the scattered ray and its attenuation, or `None` if the ray is absorbed.
     fn scatter(&self, ray: &Ray, record: &HitRecord) -> Option<(Ray, Color)>;
+    /// Light emitted by the surface at the given texture coordinates.
     fn emitted(&self, u: f32, v: f32, point: &Vec3) -> Vec3;
+
+    /// Scattering function times the cosine term for light arriving from `direction` and leaving
+    /// against `ray.direction`. Only needed by materials that are not specular.
+    fn eval(&self, _ray: &Ray, _record: &HitRecord, _direction: &Vec3) -> Color
+    {
+        Color::new_zero()
+    }
+
+    /// Solid angle density with which `scatter` picks `direction`.
+    fn pdf(&self, _ray: &Ray, _record: &HitRecord, _direction: &Vec3) -> f32
+    {
+        0.0
+    }
+
+    /// Specular materials scatter into directions that light sampling can never pick, so they
+    /// only receive light through `scatter`.
+    fn is_specular(&self) -> bool
+    {
+        true
+    }
+}
+
+impl<M: Material + ?Sized> Material for Arc<M>
+{
+    fn scatter(&self, ray: &Ray, record: &HitRecord) -> Option<(Ray, Color)> {
+        (**self).scatter(ray, record)
+    }
+
+    fn emitted(&self, u: f32, v: f32, point: &Vec3) -> Vec3 {
+        (**self).emitted(u, v, point)
+    }
+
+    fn eval(&self, ray: &Ray, record: &HitRecord, direction: &Vec3) -> Color {
+        (**self).eval(ray, record, direction)
+    }
+
+    fn pdf(&self, ray: &Ray, record: &HitRecord, direction: &Vec3) -> f32 {
+        (**self).pdf(ray, record, direction)
+    }
+
+    fn is_specular(&self) -> bool {
+        (**self).is_specular()
+    }
 }
 
 fn reflect(v: &Vec3, n: &Vec3) -> Vec3
@@ -24,6 +73,7 @@ fn refract(uv: &Vec3, n: &Vec3, eta_over_etap: f32) -> Vec3
     let r_parallel = -(1.0 - r_perpendicular.length_squared()).abs().sqrt() * *n;
     r_perpendicular + r_parallel
 }
+/// Ideal diffuse reflector.
 #[derive(Clone)]
 pub struct Lambertian<T: Texture>
 {
@@ -49,8 +99,27 @@ impl<T: Texture> Material for Lambertian<T> {
     fn emitted(&self, _u: f32, _v: f32, _point: &Vec3) -> Vec3 {
         Vec3::new_zero()
     }
+
+    fn eval(&self, _ray: &Ray, record: &HitRecord, direction: &Vec3) -> Color {
+        let cosine = record.normal.dot(*direction) / direction.length();
+        if cosine <= 0.0
+        {
+            return Color::new_zero();
+        }
+        self.albedo.value(record.u, record.v, &record.point) * (cosine / std::f32::consts::PI)
+    }
+
+    // `scatter` offsets the normal by a random unit vector, which is cosine distributed.
+    fn pdf(&self, _ray: &Ray, record: &HitRecord, direction: &Vec3) -> f32 {
+        (record.normal.dot(*direction) / direction.length()).max(0.0) / std::f32::consts::PI
+    }
+
+    fn is_specular(&self) -> bool {
+        false
+    }
 }
 
+/// Mirror reflection, blurred by `fuzz`.
 #[derive(Clone)]
 pub struct Metal
 {
@@ -81,8 +150,52 @@ impl Material for Metal
     fn emitted(&self, _u: f32, _v: f32, _point: &Vec3) -> Vec3 {
         Vec3::new_zero()
     }
+
+    // Every direction `scatter` keeps is weighted by the albedo alone, so the scattering function
+    // times the cosine is the albedo times the sampling density.
+    fn eval(&self, ray: &Ray, record: &HitRecord, direction: &Vec3) -> Color {
+        if direction.dot(record.normal) <= 0.0
+        {
+            return Color::new_zero();
+        }
+        self.albedo * self.pdf(ray, record, direction)
+    }
+
+    // `scatter` picks a uniform point on a sphere of radius `fuzz` around the mirror direction.
+    // The density of `direction` sums the area density of that sphere, converted to solid angle,
+    // over the points where the direction pierces it.
+    fn pdf(&self, ray: &Ray, record: &HitRecord, direction: &Vec3) -> f32 {
+        if self.fuzz <= 0.0
+        {
+            return 0.0;
+        }
+
+        let reflected = reflect(&ray.direction, &record.normal).normalize();
+        let direction = direction.normalize();
+        let b = direction.dot(reflected);
+        let discriminant = b * b - (1.0 - self.fuzz * self.fuzz);
+        if discriminant < 0.0
+        {
+            return 0.0;
+        }
+
+        let sqrt = discriminant.sqrt();
+        [b - sqrt, b + sqrt].iter()
+            .filter(|&&t| t > 0.0)
+            .map(|&t| {
+                let sphere_normal = (t * direction - reflected) / self.fuzz;
+                let cosine = sphere_normal.dot(direction).abs().max(1e-4);
+                t * t / (cosine * 4.0 * std::f32::consts::PI * self.fuzz * self.fuzz)
+            })
+            .sum()
+    }
+
+    fn is_specular(&self) -> bool {
+        self.fuzz <= 0.0
+    }
 }
 
+/// Clear refracting material such as glass or water.
 #[derive(Clone)]
 pub struct Dielectric
 {
@@ -124,6 +237,7 @@ impl Material for Dielectric
     }
 }
 
+/// Light source emitting its texture in every direction.
 #[derive(Clone)]
 pub struct DiffuseMaterial<T: Texture>
 {
@@ -140,7 +254,106 @@ impl<T: Texture> Material for DiffuseMaterial<T> {
     }
 
     fn emitted(&self, u: f32, v: f32, point: &Vec3) -> Vec3 {
-        return self.texture.value(u, v, point);
+        self.texture.value(u, v, point)
+    }
+}
+
+/// Phase function of a participating medium that scatters equally in every direction.
+#[derive(Clone)]
+pub struct Isotropic<T: Texture>
+{
+    albedo: T,
+}
+
+impl<T: Texture> Isotropic<T> {
+    pub fn new(albedo: T) -> Isotropic<T> { Isotropic { albedo } }
+}
+
+impl<T: Texture> Material for Isotropic<T> {
+    fn scatter(&self, ray: &Ray, record: &HitRecord) -> Option<(Ray, Color)> {
+        Some((Ray::new(record.point, Vec3::random_unit_vector(), ray.time), self.albedo.value(record.u, record.v, &record.point)))
+    }
+
+    fn emitted(&self, _u: f32, _v: f32, _point: &Vec3) -> Vec3 {
+        Vec3::new_zero()
+    }
+
+    // Phase functions have no cosine term, so this is the albedo times the uniform density.
+    fn eval(&self, ray: &Ray, record: &HitRecord, direction: &Vec3) -> Color {
+        self.albedo.value(record.u, record.v, &record.point) * self.pdf(ray, record, direction)
+    }
+
+    fn pdf(&self, _ray: &Ray, _record: &HitRecord, _direction: &Vec3) -> f32 {
+        1.0 / (4.0 * std::f32::consts::PI)
+    }
+
+    fn is_specular(&self) -> bool {
+        false
+    }
+}
+
+/// Anisotropic phase function of a participating medium. Positive `g` scatters mostly forward,
+/// negative `g` mostly backward and zero in every direction equally.
+#[derive(Clone)]
+pub struct HenyeyGreenstein<T: Texture>
+{
+    albedo: T,
+    g: f32,
+}
+
+impl<T: Texture> HenyeyGreenstein<T> {
+    /// Panics unless `g` lies strictly between -1 and 1.
+    pub fn new(albedo: T, g: f32) -> HenyeyGreenstein<T>
+    {
+        assert!(g.abs() < 1.0, "Henyey-Greenstein asymmetry must lie strictly between -1 and 1, got {g}");
+        HenyeyGreenstein { albedo, g }
+    }
+
+    // Density of scattering by an angle whose cosine is `cos_theta`, per unit solid angle.
+    fn phase(&self, cos_theta: f32) -> f32
+    {
+        let g = self.g;
+        let denominator = 1.0 + g * g - 2.0 * g * cos_theta;
+        (1.0 - g * g) / (4.0 * std::f32::consts::PI * denominator * denominator.sqrt())
+    }
+}
+
+impl<T: Texture> Material for HenyeyGreenstein<T> {
+    // Inverts the cumulative distribution of the scattering angle around the incoming direction.
+    fn scatter(&self, ray: &Ray, record: &HitRecord) -> Option<(Ray, Color)> {
+        let mut rng = rand::thread_rng();
+        let g = self.g;
+        let xi = rng.gen::<f32>();
+
+        let cos_theta = if g.abs() < 1e-3
+        {
+            1.0 - 2.0 * xi
+        } else {
+            let s = (1.0 - g * g) / (1.0 - g + 2.0 * g * xi);
+            ((1.0 + g * g - s * s) / (2.0 * g)).clamp(-1.0, 1.0)
+        };
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = std::f32::consts::TAU * rng.gen::<f32>();
+
+        let local = Vec3::new(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta);
+        let direction = Onb::new(&ray.direction).transform(local);
+        Some((Ray::new(record.point, direction, ray.time), self.albedo.value(record.u, record.v, &record.point)))
+    }
+
+    fn emitted(&self, _u: f32, _v: f32, _point: &Vec3) -> Vec3 {
+        Vec3::new_zero()
+    }
+
+    fn eval(&self, ray: &Ray, record: &HitRecord, direction: &Vec3) -> Color {
+        self.albedo.value(record.u, record.v, &record.point) * self.pdf(ray, record, direction)
+    }
+
+    fn pdf(&self, ray: &Ray, _record: &HitRecord, direction: &Vec3) -> f32 {
+        self.phase(ray.direction.dot(*direction) / (ray.direction.length() * direction.length()))
+    }
+
+    fn is_specular(&self) -> bool {
+        false
     }
 }
 
@@ -0,0 +1,251 @@
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::bvh::FlatBvh;
+use crate::color::Color;
+use crate::hittable::{HitRecord, Hittable, HittableList};
+use crate::material::{Dielectric, DiffuseMaterial, Lambertian, Material, Metal};
+use crate::ray::Ray;
+use crate::texture::{ImageTexture, SolidColor};
+use crate::triangle::{interpolate, intersect_triangle, set_shading_normal};
+use crate::vec3::Vec3;
+
+struct Face
+{
+    positions: [usize; 3],
+    normals: [usize; 3],
+    uvs: Option<[usize; 3]>,
+    material: usize,
+}
+
+// Vertex buffers shared by every triangle of a mesh.
+struct MeshData
+{
+    positions: Vec<Vec3>,
+    normals: Vec<Vec3>,
+    uvs: Vec<(f32, f32)>,
+    faces: Vec<Face>,
+    materials: Vec<Arc<dyn Material>>,
+}
+
+struct MeshTriangle
+{
+    mesh: Arc<MeshData>,
+    face: usize,
+}
+
+impl MeshTriangle
+{
+    fn vertices(&self) -> [Vec3; 3]
+    {
+        self.mesh.faces[self.face].positions.map(|i| self.mesh.positions[i])
+    }
+}
+
+impl Hittable for MeshTriangle {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        let vertices = self.vertices();
+        let (t, b1, b2) = intersect_triangle(ray, &vertices, t_min, t_max)?;
+        let face = &self.mesh.faces[self.face];
+
+        let (u, v) = match face.uvs {
+            Some(uvs) => {
+                let uvs = uvs.map(|i| self.mesh.uvs[i]);
+                (interpolate(&uvs.map(|uv| uv.0), b1, b2), interpolate(&uvs.map(|uv| uv.1), b1, b2))
+            }
+            None => (b1, b2),
+        };
+
+        let mut hit_record = HitRecord {
+            point: ray.at(t),
+            normal: (vertices[1] - vertices[0]).cross(vertices[2] - vertices[0]).normalize(),
+            t,
+            material: &*self.mesh.materials[face.material],
+            front_face: false,
+            u,
+            v,
+        };
+
+        let shading_normal = interpolate(&face.normals.map(|i| self.mesh.normals[i]), b1, b2).normalize();
+        set_shading_normal(&mut hit_record, ray, shading_normal);
+
+        Some(hit_record)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let [a, b, c] = self.vertices();
+        Aabb::surrounding(&Aabb::new(a, b), &Aabb::new(c, c))
+    }
+}
+
+/// Reason a [`Mesh`] could not be loaded.
+#[derive(Debug)]
+pub enum MeshError
+{
+    Obj(tobj::LoadError),
+    /// The OBJ file refers to an MTL library that could not be read.
+    Materials(tobj::LoadError),
+    Texture(String, image::ImageError),
+}
+
+impl Display for MeshError
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MeshError::Obj(error) => write!(f, "{error}"),
+            MeshError::Materials(error) => write!(f, "could not load materials: {error}"),
+            MeshError::Texture(path, error) => write!(f, "could not load texture {path}: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for MeshError {}
+
+/// Triangle mesh loaded from a Wavefront OBJ file, with its MTL materials mapped onto the
+/// renderer's materials.
+pub struct Mesh
+{
+    bvh: FlatBvh,
+}
+
+impl Mesh
+{
+    pub fn load_obj(path: &str) -> Result<Mesh, MeshError>
+    {
+        let options = tobj::LoadOptions { triangulate: true, ..Default::default() };
+        let (models, materials) = tobj::load_obj(path, &options).map_err(MeshError::Obj)?;
+        let directory = Path::new(path).parent().unwrap_or(Path::new(""));
+
+        let mut materials = materials.map_err(MeshError::Materials)?
+            .iter()
+            .map(|m| convert_material(m, directory))
+            .collect::<Result<Vec<_>, MeshError>>()?;
+        let default_material = materials.len();
+        materials.push(Arc::new(Lambertian::new(SolidColor::new(0.73, 0.73, 0.73))));
+
+        let mut data = MeshData { positions: vec![], normals: vec![], uvs: vec![], faces: vec![], materials };
+
+        for model in &models {
+            let mesh = &model.mesh;
+            let position_offset = data.positions.len();
+            let normal_offset = data.normals.len();
+            let uv_offset = data.uvs.len();
+
+            data.positions.extend(mesh.positions.chunks_exact(3).map(|p| Vec3::new(p[0], p[1], p[2])));
+            data.normals.extend(mesh.normals.chunks_exact(3).map(|n| Vec3::new(n[0], n[1], n[2])));
+            data.uvs.extend(mesh.texcoords.chunks_exact(2).map(|uv| (uv[0], uv[1])));
+
+            let material = mesh.material_id.filter(|&id| id < default_material).unwrap_or(default_material);
+            let smooth_normal_offset = if mesh.normal_indices.is_empty()
+            {
+                let offset = data.normals.len();
+                data.normals.extend(smooth_normals(&data.positions[position_offset..], &mesh.indices));
+                Some(offset)
+            } else {
+                None
+            };
+
+            for f in 0..mesh.indices.len() / 3 {
+                let corner = |indices: &[u32], offset: usize| [0, 1, 2].map(|k| indices[3 * f + k] as usize + offset);
+
+                data.faces.push(Face {
+                    positions: corner(&mesh.indices, position_offset),
+                    normals: match smooth_normal_offset {
+                        Some(offset) => corner(&mesh.indices, offset),
+                        None => corner(&mesh.normal_indices, normal_offset),
+                    },
+                    uvs: (!mesh.texcoord_indices.is_empty()).then(|| corner(&mesh.texcoord_indices, uv_offset)),
+                    material,
+                });
+            }
+        }
+
+        let data = Arc::new(data);
+        let triangles: Vec<Box<dyn Hittable>> = (0..data.faces.len())
+            .map(|face| Box::new(MeshTriangle { mesh: data.clone(), face }) as Box<dyn Hittable>)
+            .collect();
+
+        Ok(Mesh { bvh: FlatBvh::new(HittableList::new(triangles)) })
+    }
+}
+
+impl Hittable for Mesh {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        self.bvh.hit(ray, t_min, t_max)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bvh.bounding_box()
+    }
+}
+
+// Area weighted vertex normals, used when the OBJ file does not provide any.
+fn smooth_normals(positions: &[Vec3], indices: &[u32]) -> Vec<Vec3>
+{
+    let mut normals = vec![Vec3::new_zero(); positions.len()];
+
+    for triangle in indices.chunks_exact(3) {
+        let [a, b, c] = [0, 1, 2].map(|k| triangle[k] as usize);
+        let face_normal = (positions[b] - positions[a]).cross(positions[c] - positions[a]);
+        for i in [a, b, c] {
+            normals[i] = normals[i] + face_normal;
+        }
+    }
+
+    normals.into_iter().map(|n| if n.near_zero() { Vec3::new(0.0, 1.0, 0.0) } else { n.normalize() }).collect()
+}
+
+fn luminance(color: &Color) -> f32
+{
+    0.2126 * color.x() + 0.7152 * color.y() + 0.0722 * color.z()
+}
+
+// Maps an MTL entry onto the closest material the renderer supports: emissive entries become
+// lights, transparent ones glass, predominantly specular ones metal and everything else diffuse.
+fn convert_material(material: &tobj::Material, directory: &Path) -> Result<Arc<dyn Material>, MeshError>
+{
+    let color = |c: Option<[f32; 3]>| c.map(|c| Color::new(c[0], c[1], c[2]));
+    let image_texture = |name: &str| {
+        let path = directory.join(name.replace('\\', "/")).to_string_lossy().into_owned();
+        ImageTexture::new(&path).map_err(|error| MeshError::Texture(path, error))
+    };
+
+    let emission = material.unknown_param.get("Ke")
+        .and_then(|ke| {
+            let values: Vec<f32> = ke.split_whitespace().filter_map(|v| v.parse().ok()).collect();
+            (values.len() == 3).then(|| Color::new(values[0], values[1], values[2]))
+        })
+        .filter(|ke| !ke.near_zero());
+    let emission_texture = material.unknown_param.get("map_Ke");
+
+    if let Some(texture) = emission_texture
+    {
+        return Ok(Arc::new(DiffuseMaterial::new(image_texture(texture)?)));
+    }
+    if let Some(ke) = emission
+    {
+        return Ok(Arc::new(DiffuseMaterial::new(SolidColor::new(ke.x(), ke.y(), ke.z()))));
+    }
+
+    let illumination = material.illumination_model.unwrap_or(2);
+    if material.dissolve.is_some_and(|d| d < 1.0) || matches!(illumination, 4 | 6 | 7 | 9)
+    {
+        return Ok(Arc::new(Dielectric::new(material.optical_density.unwrap_or(1.5))));
+    }
+
+    let diffuse = color(material.diffuse).unwrap_or(Color::new(0.73, 0.73, 0.73));
+    let specular = color(material.specular).unwrap_or(Color::new_zero());
+    if matches!(illumination, 3 | 5 | 8) || luminance(&specular) > luminance(&diffuse)
+    {
+        let fuzz = (2.0 / (material.shininess.unwrap_or(0.0) + 2.0)).sqrt();
+        let albedo = if specular.near_zero() { diffuse } else { specular };
+        return Ok(Arc::new(Metal::new(albedo, fuzz)));
+    }
+
+    Ok(match &material.diffuse_texture {
+        Some(texture) => Arc::new(Lambertian::new(image_texture(texture)?)),
+        None => Arc::new(Lambertian::new(SolidColor::new(diffuse.x(), diffuse.y(), diffuse.z()))),
+    })
+}
@@ -0,0 +1,34 @@
+use crate::vec3::Vec3;
+
+/// Orthonormal basis whose `w` axis points along a given direction.
+#[derive(Clone, Copy)]
+pub struct Onb
+{
+    pub u: Vec3,
+    pub v: Vec3,
+    pub w: Vec3,
+}
+
+impl Onb
+{
+    pub fn new(n: &Vec3) -> Onb
+    {
+        let w = n.normalize();
+        let a = if w.x().abs() > 0.9 { Vec3::new(0.0, 1.0, 0.0) } else { Vec3::new(1.0, 0.0, 0.0) };
+        let v = w.cross(a).normalize();
+        let u = w.cross(v);
+        Onb { u, v, w }
+    }
+
+    /// Converts coordinates relative to the basis into world space.
+    pub fn transform(&self, local: Vec3) -> Vec3
+    {
+        local.x() * self.u + local.y() * self.v + local.z() * self.w
+    }
+
+    /// Coordinates of a world space vector relative to the basis.
+    pub fn to_local(&self, world: Vec3) -> Vec3
+    {
+        Vec3::new(world.dot(self.u), world.dot(self.v), world.dot(self.w))
+    }
+}
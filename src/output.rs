@@ -0,0 +1,88 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+use image::codecs::pnm::{PnmEncoder, PnmSubtype, SampleEncoding};
+use image::{ImageBuffer, ImageFormat, ImageResult, Rgb, Rgb32FImage, RgbImage};
+
+use crate::color::{linear_to_gamma, Color};
+use crate::framebuffer::Framebuffer;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum OutputFormat
+{
+    Png,
+    Png16,
+    Jpeg,
+    Ppm,
+    Exr,
+    Hdr,
+}
+
+impl OutputFormat
+{
+    /// Picks the format from the file extension. PNG files are written with 8 bits per channel
+    /// unless `high_bit_depth` is set.
+    pub fn from_path(path: &Path, high_bit_depth: bool) -> Option<OutputFormat>
+    {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "png" if high_bit_depth => Some(OutputFormat::Png16),
+            "png" => Some(OutputFormat::Png),
+            "jpg" | "jpeg" => Some(OutputFormat::Jpeg),
+            "ppm" => Some(OutputFormat::Ppm),
+            "exr" => Some(OutputFormat::Exr),
+            "hdr" => Some(OutputFormat::Hdr),
+            _ => None,
+        }
+    }
+}
+
+// Low dynamic range formats store gamma corrected values clamped to [0, 1].
+fn display_value(linear: f32) -> f32
+{
+    linear_to_gamma(linear.max(0.0)).min(1.0)
+}
+
+fn channels(pixel: &Color) -> [f32; 3]
+{
+    [pixel.x(), pixel.y(), pixel.z()]
+}
+
+/// Gamma corrected 8-bit encoding of a framebuffer.
+pub fn to_rgb8(framebuffer: &Framebuffer) -> RgbImage
+{
+    let data = framebuffer.pixels().iter().flat_map(channels).map(|c| (display_value(c) * 255.999) as u8).collect();
+    RgbImage::from_raw(framebuffer.width(), framebuffer.height(), data).unwrap()
+}
+
+/// Gamma corrected 16-bit encoding of a framebuffer.
+pub fn to_rgb16(framebuffer: &Framebuffer) -> ImageBuffer<Rgb<u16>, Vec<u16>>
+{
+    let data = framebuffer.pixels().iter().flat_map(channels).map(|c| (display_value(c) * 65535.0).round() as u16).collect();
+    ImageBuffer::from_raw(framebuffer.width(), framebuffer.height(), data).unwrap()
+}
+
+/// Linear floating point copy of a framebuffer, as stored by the HDR formats.
+pub fn to_rgb32f(framebuffer: &Framebuffer) -> Rgb32FImage
+{
+    let data = framebuffer.pixels().iter().flat_map(channels).collect();
+    Rgb32FImage::from_raw(framebuffer.width(), framebuffer.height(), data).unwrap()
+}
+
+/// Encodes a framebuffer to a file. HDR formats keep the unclamped linear values, every other
+/// format is gamma corrected and quantized.
+pub fn write_image(path: &Path, format: OutputFormat, framebuffer: &Framebuffer) -> ImageResult<()>
+{
+    match format {
+        OutputFormat::Exr => to_rgb32f(framebuffer).save_with_format(path, ImageFormat::OpenExr),
+        OutputFormat::Hdr => to_rgb32f(framebuffer).save_with_format(path, ImageFormat::Hdr),
+        OutputFormat::Png16 => to_rgb16(framebuffer).save_with_format(path, ImageFormat::Png),
+        OutputFormat::Png => to_rgb8(framebuffer).save_with_format(path, ImageFormat::Png),
+        OutputFormat::Jpeg => to_rgb8(framebuffer).save_with_format(path, ImageFormat::Jpeg),
+        OutputFormat::Ppm => {
+            let writer = BufWriter::new(File::create(path)?);
+            to_rgb8(framebuffer).write_with_encoder(PnmEncoder::new(writer).with_subtype(PnmSubtype::Pixmap(SampleEncoding::Binary)))
+        }
+    }
+}
@@ -55,6 +55,7 @@ fn permute(p: &mut [usize], n: usize)
     }
 }
 
+/// Gradient noise generator.
 #[derive(Clone)]
 pub struct Perlin
 {
@@ -72,6 +73,14 @@ impl MulAssign<f32> for Vec3 {
     }
 }
 
+impl Default for Perlin
+{
+    fn default() -> Self
+    {
+        Perlin::new()
+    }
+}
+
 impl Perlin
 {
     pub fn new() -> Self
@@ -97,10 +106,10 @@ impl Perlin
 
         let mut c = [[[Vec3::new_zero(); 2]; 2]; 2];
 
-        for di in 0..2 {
-            for dj in 0..2 {
-                for dk in 0..2 {
-                    c[di][dj][dk] = self.random_vectors[
+        for (di, plane) in c.iter_mut().enumerate() {
+            for (dj, row) in plane.iter_mut().enumerate() {
+                for (dk, cell) in row.iter_mut().enumerate() {
+                    *cell = self.random_vectors[
                         self.perm_x[((i + di as i32) & 255) as usize] ^
                         self.perm_y[((j + dj as i32) & 255) as usize] ^
                         self.perm_z[((k + dk as i32) & 255) as usize]];
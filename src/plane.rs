@@ -0,0 +1,103 @@
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::material::Material;
+use crate::onb::Onb;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+
+/// Infinite plane through `point`. Texture coordinates are the position along two axes of the
+/// plane divided by `tile_size`, wrapped to `[0, 1)` so textures repeat every `tile_size` units.
+pub struct Plane<T: Material>
+{
+    point: Vec3,
+    onb: Onb,
+    tile_size: f32,
+    material: T,
+    bbox: Aabb,
+}
+
+impl<T: Material> Plane<T>
+{
+    /// Panics unless `tile_size` is positive.
+    pub fn new(point: Vec3, normal: Vec3, tile_size: f32, material: T) -> Plane<T>
+    {
+        assert!(tile_size > 0.0, "plane tile size must be positive, got {tile_size}");
+        let onb = Onb::new(&normal);
+
+        // The plane only has a finite extent along an axis it is perpendicular to.
+        let mut bbox = Aabb::UNIVERSE;
+        for axis in 0..3 {
+            if (0..3).all(|other| other == axis || onb.w.e[other] == 0.0)
+            {
+                bbox.min.e[axis] = point.e[axis];
+                bbox.max.e[axis] = point.e[axis];
+            }
+        }
+
+        Plane { point, onb, tile_size, material, bbox: Aabb::new(bbox.min, bbox.max) }
+    }
+}
+
+impl<T: Material> Hittable for Plane<T> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        let denom = self.onb.w.dot(ray.direction);
+
+        if denom.abs() < 1e-8 { return None; }
+
+        let t = self.onb.w.dot(self.point - ray.origin) / denom;
+
+        if t < t_min || t > t_max
+        {
+            return None;
+        }
+
+        let point = ray.at(t);
+        let local = self.onb.to_local(point - self.point) / self.tile_size;
+
+        let mut hit_record = HitRecord {
+            point,
+            normal: self.onb.w,
+            t,
+            material: &self.material,
+            front_face: false,
+            u: local.x().rem_euclid(1.0),
+            v: local.y().rem_euclid(1.0),
+        };
+        hit_record.set_normal(ray);
+
+        Some(hit_record)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::material::Lambertian;
+    use crate::texture::{CheckerTexture, SolidColor, Texture};
+
+    #[test]
+    fn checker_alternates_across_tiles()
+    {
+        let plane = Plane::new(Vec3::new_zero(), Vec3::new(0.0, 1.0, 0.0), 1.0, Lambertian::new(SolidColor::new(0.5, 0.5, 0.5)));
+        let checker = CheckerTexture::new(2.0, 2.0, SolidColor::new(0.0, 0.0, 0.0), SolidColor::new(1.0, 1.0, 1.0));
+        let color = |x: f32, z: f32| {
+            let ray = Ray::new(Vec3::new(x, 1.0, z), Vec3::new(0.0, -1.0, 0.0), 0.0);
+            let hit = plane.hit(&ray, 0.001, f32::INFINITY).unwrap();
+            checker.value(hit.u, hit.v, &hit.point).x()
+        };
+
+        // Half a tile apart in either direction, within a tile and across tile boundaries.
+        for i in -4..4 {
+            for j in -4..4 {
+                let (x, z) = (0.25 + 0.5 * i as f32, 0.25 + 0.5 * j as f32);
+                assert_ne!(color(x, z), color(x + 0.5, z), "at {x}, {z}");
+                assert_ne!(color(x, z), color(x, z + 0.5), "at {x}, {z}");
+            }
+        }
+    }
+}
@@ -0,0 +1,244 @@
+//! Closed form real roots of polynomials up to degree four, computed in double precision. The
+//! quartic solver refines its roots with Newton iterations, which keeps surfaces such as the torus
+//! free of speckles where rays graze them.
+
+const EPSILON: f64 = 1e-12;
+
+fn is_zero(x: f64) -> bool
+{
+    x.abs() < EPSILON
+}
+
+/// Up to four real roots in increasing order.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Roots
+{
+    values: [f64; 4],
+    len: usize,
+}
+
+impl Roots
+{
+    fn push(&mut self, root: f64)
+    {
+        if self.len < 4 && root.is_finite()
+        {
+            self.values[self.len] = root;
+            self.len += 1;
+        }
+    }
+
+    fn sorted(mut self) -> Roots
+    {
+        self.values[..self.len].sort_by(f64::total_cmp);
+        self
+    }
+
+    fn map(mut self, f: impl Fn(f64) -> f64) -> Roots
+    {
+        for value in &mut self.values[..self.len] {
+            *value = f(*value);
+        }
+        self
+    }
+
+    pub fn as_slice(&self) -> &[f64]
+    {
+        &self.values[..self.len]
+    }
+}
+
+/// Real roots of `a·x² + b·x + c`, falling back to the linear equation when `a` is zero.
+pub fn solve_quadratic(a: f64, b: f64, c: f64) -> Roots
+{
+    let mut roots = Roots::default();
+
+    if is_zero(a)
+    {
+        if !is_zero(b)
+        {
+            roots.push(-c / b);
+        }
+        return roots;
+    }
+
+    // A double root can come out with a slightly negative discriminant after rounding, which
+    // would lose rays that touch a surface tangentially.
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < -EPSILON * (b * b).max(1.0)
+    {
+        return roots;
+    }
+    let discriminant = discriminant.max(0.0);
+
+    // Avoids the cancellation of the textbook formula when b² is much larger than 4ac.
+    let q = -0.5 * (b + b.signum() * discriminant.sqrt());
+    if is_zero(q)
+    {
+        roots.push(0.0);
+        roots.push(0.0);
+    } else {
+        roots.push(q / a);
+        roots.push(c / q);
+    }
+    roots.sorted()
+}
+
+/// Real roots of `a·x³ + b·x² + c·x + d`.
+pub fn solve_cubic(a: f64, b: f64, c: f64, d: f64) -> Roots
+{
+    if is_zero(a)
+    {
+        return solve_quadratic(b, c, d);
+    }
+
+    // Normal form x³ + A·x² + B·x + C, then x = y - A/3 removes the quadratic term.
+    let (a2, a1, a0) = (b / a, c / a, d / a);
+    let p = (3.0 * a1 - a2 * a2) / 9.0;
+    let q = (2.0 * a2 * a2 * a2 - 9.0 * a2 * a1 + 27.0 * a0) / 54.0;
+    let p3 = p * p * p;
+    let discriminant = q * q + p3;
+
+    let mut roots = Roots::default();
+    if is_zero(discriminant)
+    {
+        if is_zero(q)
+        {
+            roots.push(0.0);
+        } else {
+            let u = (-q).cbrt();
+            roots.push(2.0 * u);
+            roots.push(-u);
+        }
+    } else if discriminant < 0.0
+    {
+        // Three real roots.
+        let phi = (-q / (-p3).sqrt()).clamp(-1.0, 1.0).acos() / 3.0;
+        let t = 2.0 * (-p).sqrt();
+        roots.push(t * phi.cos());
+        roots.push(-t * (phi + std::f64::consts::FRAC_PI_3).cos());
+        roots.push(-t * (phi - std::f64::consts::FRAC_PI_3).cos());
+    } else {
+        let sqrt = discriminant.sqrt();
+        roots.push((sqrt - q).cbrt() - (sqrt + q).cbrt());
+    }
+
+    roots.map(|y| y - a2 / 3.0).sorted()
+}
+
+/// Real roots of `a·x⁴ + b·x³ + c·x² + d·x + e`.
+pub fn solve_quartic(a: f64, b: f64, c: f64, d: f64, e: f64) -> Roots
+{
+    if is_zero(a)
+    {
+        return solve_cubic(b, c, d, e);
+    }
+
+    // Normal form x⁴ + A·x³ + B·x² + C·x + D, then x = y - A/4 gives the depressed
+    // quartic y⁴ + p·y² + q·y + r.
+    let (a3, a2, a1, a0) = (b / a, c / a, d / a, e / a);
+    let sq = a3 * a3;
+    let p = -3.0 / 8.0 * sq + a2;
+    let q = sq * a3 / 8.0 - a3 * a2 / 2.0 + a1;
+    let r = -3.0 / 256.0 * sq * sq + sq * a2 / 16.0 - a3 * a1 / 4.0 + a0;
+
+    let mut roots = Roots::default();
+    if is_zero(r)
+    {
+        // y · (y³ + p·y + q) = 0
+        roots.push(0.0);
+        for &y in solve_cubic(1.0, 0.0, p, q).as_slice() {
+            roots.push(y);
+        }
+    } else {
+        // Ferrari: any real root z of the resolvent cubic splits the quartic into two quadratics.
+        let resolvent = solve_cubic(1.0, -p / 2.0, -r, r * p / 2.0 - q * q / 8.0);
+        let z = resolvent.as_slice()[resolvent.len - 1];
+
+        let u = z * z - r;
+        let v = 2.0 * z - p;
+        if u < -EPSILON || v < -EPSILON
+        {
+            return roots;
+        }
+        let u = u.max(0.0).sqrt();
+        let v = if q < 0.0 { -v.max(0.0).sqrt() } else { v.max(0.0).sqrt() };
+
+        for &y in solve_quadratic(1.0, v, z - u).as_slice() {
+            roots.push(y);
+        }
+        for &y in solve_quadratic(1.0, -v, z + u).as_slice() {
+            roots.push(y);
+        }
+    }
+
+    let polish = |mut x: f64| {
+        for _ in 0..2 {
+            let value = (((a * x + b) * x + c) * x + d) * x + e;
+            let slope = ((4.0 * a * x + 3.0 * b) * x + 2.0 * c) * x + d;
+            if is_zero(slope)
+            {
+                break;
+            }
+            x -= value / slope;
+        }
+        x
+    };
+    roots.map(|y| polish(y - a3 / 4.0)).sorted()
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn assert_roots(roots: Roots, expected: &[f64])
+    {
+        assert_eq!(roots.as_slice().len(), expected.len(), "{:?} != {expected:?}", roots.as_slice());
+        for (root, expected) in roots.as_slice().iter().zip(expected) {
+            assert!((root - expected).abs() < 1e-6, "{:?} != {expected:?}", roots.as_slice());
+        }
+    }
+
+    #[test]
+    fn quartic_with_distinct_roots()
+    {
+        // (x - 1)(x - 2)(x - 3)(x - 4)
+        assert_roots(solve_quartic(1.0, -10.0, 35.0, -50.0, 24.0), &[1.0, 2.0, 3.0, 4.0]);
+        assert_roots(solve_quartic(2.0, -20.0, 70.0, -100.0, 48.0), &[1.0, 2.0, 3.0, 4.0]);
+    }
+
+    #[test]
+    fn quartic_with_repeated_roots()
+    {
+        // (x - 1)²(x - 2)²
+        assert_roots(solve_quartic(1.0, -6.0, 13.0, -12.0, 4.0), &[1.0, 1.0, 2.0, 2.0]);
+        // (x + 1)³(x - 3)
+        assert_roots(solve_quartic(1.0, 0.0, -6.0, -8.0, -3.0), &[-1.0, -1.0, -1.0, 3.0]);
+    }
+
+    #[test]
+    fn biquadratic_quartic()
+    {
+        // (x² - 1)(x² - 4) has no odd terms, so the depressed quartic has q = 0.
+        assert_roots(solve_quartic(1.0, 0.0, -5.0, 0.0, 4.0), &[-2.0, -1.0, 1.0, 2.0]);
+        // (x² + 1)(x² - 4)
+        assert_roots(solve_quartic(1.0, 0.0, -3.0, 0.0, -4.0), &[-2.0, 2.0]);
+    }
+
+    #[test]
+    fn depressed_quartic_with_root_at_its_center()
+    {
+        // x(x - 1)(x - 2)(x + 3) is already depressed and has r = 0.
+        assert_roots(solve_quartic(1.0, 0.0, -7.0, 6.0, 0.0), &[-3.0, 0.0, 1.0, 2.0]);
+        // The same roots shifted by 5 take the same path after the substitution.
+        assert_roots(solve_quartic(1.0, -20.0, 143.0, -424.0, 420.0), &[2.0, 5.0, 6.0, 7.0]);
+    }
+
+    #[test]
+    fn quartic_without_real_roots()
+    {
+        assert_roots(solve_quartic(1.0, 0.0, 0.0, 0.0, 1.0), &[]);
+        assert_roots(solve_quartic(1.0, 0.0, 2.0, 0.0, 1.0), &[]);
+    }
+}
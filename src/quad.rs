@@ -1,8 +1,12 @@
+use rand::Rng;
+
+use crate::aabb::Aabb;
 use crate::hittable::{HitRecord, Hittable};
 use crate::material::Material;
 use crate::ray::Ray;
 use crate::vec3::Vec3;
 
+/// Parallelogram spanned by `u` and `v` from the corner `q`.
 pub struct Quad<T: Material>
 {
     q: Vec3,
@@ -12,6 +16,8 @@ pub struct Quad<T: Material>
     material: T,
     normal: Vec3,
     d: f32,
+    area: f32,
+    bbox: Aabb,
 }
 
 impl<T: Material> Quad<T>
@@ -20,12 +26,13 @@ impl<T: Material> Quad<T>
     {
         let n = u.cross(v);
         let normal = n.normalize();
-        Quad { q, u, v, w: n / n.dot(n), material, normal, d: normal.dot(q) }
+        let bbox = Aabb::surrounding(&Aabb::new(q, q + u + v), &Aabb::new(q + u, q + v));
+        Quad { q, u, v, w: n / n.dot(n), material, normal, d: normal.dot(q), area: n.length(), bbox }
     }
 }
 
 impl<T: Material> Hittable for Quad<T> {
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
         let denom = self.normal.dot(ray.direction);
 
         if denom.abs() < 1e-8 { return None; }
@@ -42,7 +49,7 @@ impl<T: Material> Hittable for Quad<T> {
         let alpha = self.w.dot(planar_hit_vector.cross(self.v));
         let beta = self.w.dot(self.u.cross(planar_hit_vector));
 
-        if alpha < 0.0 || alpha > 1.0 || beta < 0.0 || beta > 1.0
+        if !(0.0..=1.0).contains(&alpha) || !(0.0..=1.0).contains(&beta)
         {
             return None;
         }
@@ -58,6 +65,26 @@ impl<T: Material> Hittable for Quad<T> {
         };
         hit_record.set_normal(ray);
 
-        return Some(hit_record)
+        Some(hit_record)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    fn pdf(&self, origin: &Vec3, direction: &Vec3, time: f32) -> f32 {
+        match self.hit(&Ray::new(*origin, *direction, time), 0.001, f32::INFINITY) {
+            Some(hit_record) => {
+                let distance_squared = hit_record.t * hit_record.t * direction.length_squared();
+                let cosine = (direction.dot(self.normal) / direction.length()).abs();
+                distance_squared / (cosine * self.area)
+            }
+            None => 0.0,
+        }
+    }
+
+    fn sample(&self, origin: &Vec3, _time: f32) -> Vec3 {
+        let mut rng = rand::thread_rng();
+        self.q + rng.gen::<f32>() * self.u + rng.gen::<f32>() * self.v - *origin
     }
 }
\ No newline at end of file
@@ -1,10 +1,11 @@
 use crate::vec3::Vec3;
 
+/// Half-line `origin + t * direction` sampled at `time` within the shutter interval.
 #[derive(Copy, Clone)]
 pub struct Ray
 {
-    pub(crate) origin: Vec3,
-    pub(crate) direction: Vec3,
+    pub origin: Vec3,
+    pub direction: Vec3,
     pub time: f32
 }
 
@@ -17,6 +18,6 @@ impl Ray
 
     pub fn at(self, t: f32) -> Vec3
     {
-        return self.origin + t * self.direction;
+        self.origin + t * self.direction
     }
 }
\ No newline at end of file
@@ -0,0 +1,832 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::Deserialize;
+
+use crate::animation::{AnimatedTransform, Keyframe};
+use crate::box_shape::BoxShape;
+use crate::bvh::FlatBvh;
+use crate::camera::{Camera, ShutterCurve};
+use crate::cone::Cone;
+use crate::constant_medium::ConstantMedium;
+use crate::csg::{Csg, CsgOperation};
+use crate::cylinder::Cylinder;
+use crate::density::{DensityField, NoiseDensity, VoxelGrid};
+use crate::disk::{Annulus, Disk};
+use crate::heterogeneous_medium::HeterogeneousMedium;
+use crate::color::Color;
+use crate::hittable::{Hittable, HittableList};
+use crate::instance::{AnimatedInstance, Instance};
+use crate::material::{Dielectric, DiffuseMaterial, HenyeyGreenstein, Isotropic, Lambertian, Material, Metal};
+use crate::mesh::{Mesh, MeshError};
+use crate::plane::Plane;
+use crate::quad::Quad;
+use crate::sdf::{Mandelbulb, RoundedBox, Sdf, SdfHittable, SdfSphere, SmoothUnion};
+use crate::sphere::Sphere;
+use crate::texture::{CheckerTexture, ImageTexture, NoiseTexture, SolidColor, Texture};
+use crate::torus::Torus;
+use crate::triangle::Triangle;
+use crate::vec3::{Mat4, Point3, Vec3};
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SceneDescription
+{
+    camera: CameraDescription,
+    #[serde(default)]
+    textures: HashMap<String, TextureDescription>,
+    #[serde(default)]
+    materials: HashMap<String, MaterialDescription>,
+    objects: Vec<ObjectDescription>,
+}
+
+fn default_up() -> [f32; 3] { [0.0, 1.0, 0.0] }
+fn default_focus_distance() -> f32 { 10.0 }
+fn default_background() -> [f32; 3] { [0.70, 0.80, 1.00] }
+fn default_shutter_close() -> f32 { 1.0 }
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CameraDescription
+{
+    look_from: [f32; 3],
+    look_at: [f32; 3],
+    #[serde(default = "default_up")]
+    up: [f32; 3],
+    vfov: f32,
+    aspect_ratio: f32,
+    image_width: i32,
+    samples_per_pixel: i32,
+    max_depth: i32,
+    min_depth: Option<i32>,
+    #[serde(default)]
+    defocus_angle: f32,
+    #[serde(default = "default_focus_distance")]
+    focus_distance: f32,
+    #[serde(default = "default_background")]
+    background: [f32; 3],
+    #[serde(default)]
+    shutter_open: f32,
+    #[serde(default = "default_shutter_close")]
+    shutter_close: f32,
+    #[serde(default)]
+    shutter_curve: ShutterCurveDescription,
+    /// Camera positions over time for animations. `look_from`, `look_at` and `up` above are
+    /// still required and used for the first frame when this is empty.
+    #[serde(default)]
+    keyframes: Vec<CameraKeyframeDescription>,
+    /// Index of an object to frame. `look_from` and `look_at` are then offsets from the center of
+    /// its bounding box, measured in lengths of the box's diagonal.
+    frame_object: Option<usize>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CameraKeyframeDescription
+{
+    time: f32,
+    look_from: [f32; 3],
+    look_at: [f32; 3],
+    #[serde(default = "default_up")]
+    up: [f32; 3],
+}
+
+#[derive(Deserialize, Default)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum ShutterCurveDescription
+{
+    #[default]
+    Box,
+    Triangle,
+    Rolling { exposure: f32 },
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum TextureDescription
+{
+    Solid { color: [f32; 3] },
+    Checker { width: f32, height: f32, even: TextureReference, odd: TextureReference },
+    Image { path: String },
+    Noise { scale: f32 },
+}
+
+/// Either the name of a texture declared in the `textures` table or an inline RGB color.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TextureReference
+{
+    Color([f32; 3]),
+    Named(String),
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum MaterialDescription
+{
+    Lambertian { texture: TextureReference },
+    Metal { albedo: [f32; 3], fuzz: f32 },
+    Dielectric { refraction_index: f32 },
+    DiffuseLight { texture: TextureReference },
+    Isotropic { texture: TextureReference },
+    HenyeyGreenstein { texture: TextureReference, g: f32 },
+}
+
+/// Either the name of a material declared in the `materials` table or an inline material.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum MaterialReference
+{
+    Named(String),
+    Inline(MaterialDescription),
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum ObjectDescription
+{
+    Sphere { center: [f32; 3], center2: Option<[f32; 3]>, radius: f32, material: MaterialReference },
+    Quad { q: [f32; 3], u: [f32; 3], v: [f32; 3], material: MaterialReference },
+    Triangle { vertices: [[f32; 3]; 3], normals: Option<[[f32; 3]; 3]>, uvs: Option<[(f32, f32); 3]>, material: MaterialReference },
+    Box { min: [f32; 3], max: [f32; 3], material: MaterialReference },
+    Cylinder { base: [f32; 3], top: [f32; 3], radius: f32, #[serde(default = "default_capped")] capped: bool, material: MaterialReference },
+    Cone { base: [f32; 3], apex: [f32; 3], radius: f32, #[serde(default = "default_capped")] capped: bool, material: MaterialReference },
+    Disk { center: [f32; 3], normal: [f32; 3], radius: f32, material: MaterialReference },
+    Annulus { center: [f32; 3], normal: [f32; 3], inner_radius: f32, outer_radius: f32, material: MaterialReference },
+    Torus { center: [f32; 3], axis: [f32; 3], major_radius: f32, minor_radius: f32, material: MaterialReference },
+    Plane { point: [f32; 3], normal: [f32; 3], #[serde(default = "default_tile_size")] tile_size: f32, material: MaterialReference },
+    /// Shape given by a signed distance function, rendered by sphere tracing.
+    Sdf { shape: SdfDescription, material: MaterialReference },
+    Mesh { path: String },
+    /// The small spheres of the bouncing spheres scene, scattered at random over a 22 by 22 grid
+    /// around the origin. The same `seed` always gives the same spheres.
+    RandomSpheres { seed: Option<u64> },
+    /// Fills a boundary object with fog; the material of the boundary itself is never used.
+    ConstantMedium { boundary: Box<ObjectDescription>, density: f32, material: MaterialReference },
+    /// Like `ConstantMedium`, with the density varying through the boundary.
+    HeterogeneousMedium { boundary: Box<ObjectDescription>, density: DensityDescription, material: MaterialReference },
+    /// Constructive solid geometry of two closed objects.
+    Union { a: Box<ObjectDescription>, b: Box<ObjectDescription> },
+    Intersection { a: Box<ObjectDescription>, b: Box<ObjectDescription> },
+    /// Cuts `b` out of `a`.
+    Difference { a: Box<ObjectDescription>, b: Box<ObjectDescription> },
+    /// Another object moved by a list of transforms, applied in order.
+    Instance { object: Box<ObjectDescription>, transform: Vec<TransformDescription> },
+    /// Another object moving between keyframes, each placing it with a list of transforms.
+    Animated { object: Box<ObjectDescription>, keyframes: Vec<KeyframeDescription> },
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct KeyframeDescription
+{
+    time: f32,
+    transform: Vec<TransformDescription>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+enum TransformDescription
+{
+    Translate([f32; 3]),
+    Scale([f32; 3]),
+    RotateX(f32),
+    RotateY(f32),
+    RotateZ(f32),
+    Rotate { axis: [f32; 3], degrees: f32 },
+}
+
+impl TransformDescription
+{
+    // Applies the transforms in order, failing if they cannot be undone.
+    fn compose(transforms: &[TransformDescription]) -> Result<Mat4, SceneError>
+    {
+        let matrix = transforms.iter().fold(Mat4::identity(), |matrix, t| t.matrix() * matrix);
+        match matrix.inverse() {
+            Some(_) => Ok(matrix),
+            None => Err(SceneError::SingularTransform),
+        }
+    }
+
+    fn matrix(&self) -> Mat4
+    {
+        match self {
+            TransformDescription::Translate(offset) => Mat4::translation(vec3(*offset)),
+            TransformDescription::Scale(factors) => Mat4::scaling(vec3(*factors)),
+            TransformDescription::RotateX(degrees) => Mat4::rotation_x(*degrees),
+            TransformDescription::RotateY(degrees) => Mat4::rotation_y(*degrees),
+            TransformDescription::RotateZ(degrees) => Mat4::rotation_z(*degrees),
+            TransformDescription::Rotate { axis, degrees } => Mat4::rotation(vec3(*axis), *degrees),
+        }
+    }
+}
+
+fn default_capped() -> bool { true }
+fn default_mandelbulb_power() -> f32 { 8.0 }
+fn default_mandelbulb_iterations() -> u32 { 12 }
+fn default_tile_size() -> f32 { 1.0 }
+fn default_turbulence_depth() -> i32 { 7 }
+fn default_density_scale() -> f32 { 1.0 }
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum DensityDescription
+{
+    Noise { scale: f32, density: f32, #[serde(default = "default_turbulence_depth")] depth: i32 },
+    VoxelGrid { path: String, #[serde(default = "default_density_scale")] scale: f32 },
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum SdfDescription
+{
+    Sphere { center: [f32; 3], radius: f32 },
+    RoundedBox { center: [f32; 3], size: [f32; 3], radius: f32 },
+    Mandelbulb {
+        center: [f32; 3],
+        scale: f32,
+        #[serde(default = "default_mandelbulb_power")] power: f32,
+        #[serde(default = "default_mandelbulb_iterations")] iterations: u32,
+    },
+    SmoothUnion { a: Box<SdfDescription>, b: Box<SdfDescription>, smoothness: f32 },
+}
+
+#[derive(Debug)]
+pub enum SceneError
+{
+    Io(std::io::Error),
+    Parse(serde_json::Error),
+    Invalid(String),
+    UnknownTexture(String),
+    UnknownMaterial(String),
+    RecursiveTexture(String),
+    Texture(String, image::ImageError),
+    Mesh(String, MeshError),
+    VoxelGrid(String, std::io::Error),
+    SingularTransform,
+    NoKeyframes,
+    ShearedKeyframe(f32),
+    FrameObject(usize),
+}
+
+impl Display for SceneError
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SceneError::Io(error) => write!(f, "could not read scene file: {error}"),
+            SceneError::Parse(error) => write!(f, "invalid scene description: {error}"),
+            SceneError::Invalid(message) => write!(f, "invalid scene description: {message}"),
+            SceneError::UnknownTexture(name) => write!(f, "unknown texture \"{name}\""),
+            SceneError::UnknownMaterial(name) => write!(f, "unknown material \"{name}\""),
+            SceneError::RecursiveTexture(name) => write!(f, "texture \"{name}\" refers to itself"),
+            SceneError::Texture(path, error) => write!(f, "could not load texture {path}: {error}"),
+            SceneError::Mesh(path, error) => write!(f, "could not load mesh {path}: {error}"),
+            SceneError::VoxelGrid(path, error) => write!(f, "could not load voxel grid {path}: {error}"),
+            SceneError::SingularTransform => write!(f, "instance transform cannot be inverted"),
+            SceneError::NoKeyframes => write!(f, "animated object has no keyframes"),
+            SceneError::ShearedKeyframe(time) => write!(f, "keyframe at time {time} shears the object, which cannot be interpolated"),
+            SceneError::FrameObject(index) => write!(f, "camera cannot frame object {index}, which is missing or unbounded"),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+/// A camera together with the objects it looks at, as described by a JSON scene file. Objects
+/// with a `diffuse_light` material are also added to `lights` so they can be sampled directly.
+///
+/// Animations measure time in frames: frame `n`, counted from 1, is exposed from `n - 1` plus
+/// the camera's shutter opening time to `n - 1` plus its closing time.
+pub struct Scene
+{
+    pub camera: Camera,
+    pub world: HittableList,
+    pub lights: HittableList,
+    /// Pose of the camera over time, if it moves.
+    pub camera_path: Option<AnimatedTransform>,
+}
+
+impl Scene
+{
+    /// Loads a scene file. Relative texture and mesh paths are resolved against the directory
+    /// containing the file.
+    pub fn load(path: &str) -> Result<Scene, SceneError>
+    {
+        let source = fs::read_to_string(path).map_err(SceneError::Io)?;
+        let directory = Path::new(path).parent().unwrap_or(Path::new(""));
+        Scene::parse(&source, directory)
+    }
+
+    /// Parses a scene description. Relative texture and mesh paths are resolved against `directory`.
+    pub fn parse(source: &str, directory: &Path) -> Result<Scene, SceneError>
+    {
+        let description: SceneDescription = serde_json::from_str(source).map_err(SceneError::Parse)?;
+        let mut builder = SceneBuilder { description: &description, directory, textures: HashMap::new(), materials: HashMap::new() };
+
+        let mut world = HittableList::new(vec![]);
+        let mut lights = HittableList::new(vec![]);
+        let mut bounding_boxes = Vec::with_capacity(description.objects.len());
+        for object in &description.objects {
+            let hittable = builder.object(object)?;
+            bounding_boxes.push(hittable.bounding_box());
+            if builder.is_light(object)
+            {
+                let light: Arc<dyn Hittable> = Arc::from(hittable);
+                world.objects.push(Box::new(light.clone()));
+                lights.objects.push(Box::new(light));
+            } else {
+                world.objects.push(hittable);
+            }
+        }
+
+        let c = &description.camera;
+        let (origin, unit) = match c.frame_object {
+            Some(index) => {
+                let bbox = bounding_boxes.get(index)
+                    .filter(|bbox| !bbox.is_unbounded() && bbox.min.x() <= bbox.max.x())
+                    .ok_or(SceneError::FrameObject(index))?;
+                (bbox.centroid(), (bbox.max - bbox.min).length())
+            }
+            None => (Vec3::new_zero(), 1.0),
+        };
+        let place = |p: [f32; 3]| Point3(origin + unit * vec3(p));
+
+        if c.shutter_close < c.shutter_open
+        {
+            return Err(SceneError::Invalid(format!("shutter closes at {} before it opens at {}", c.shutter_close, c.shutter_open)));
+        }
+        if let ShutterCurveDescription::Rolling { exposure } = c.shutter_curve
+        {
+            if exposure <= 0.0 || exposure > 1.0
+            {
+                return Err(SceneError::Invalid(format!("rolling shutter exposure must be above 0 and at most 1, got {exposure}")));
+            }
+        }
+
+        let mut camera = Camera::new(
+            c.focus_distance,
+            c.defocus_angle,
+            c.vfov,
+            &Mat4::look_at(place(c.look_from), place(c.look_at), vec3(c.up)),
+            c.aspect_ratio,
+            c.image_width,
+        );
+        camera.samples_per_pixel = c.samples_per_pixel;
+        camera.max_depth = c.max_depth;
+        camera.background = vec3(c.background);
+        camera.shutter_open = c.shutter_open;
+        camera.shutter_close = c.shutter_close;
+        camera.shutter_curve = match c.shutter_curve {
+            ShutterCurveDescription::Box => ShutterCurve::Box,
+            ShutterCurveDescription::Triangle => ShutterCurve::Triangle,
+            ShutterCurveDescription::Rolling { exposure } => ShutterCurve::Rolling { exposure },
+        };
+        if let Some(min_depth) = c.min_depth
+        {
+            camera.min_depth = min_depth;
+        }
+
+        let camera_path = if c.keyframes.is_empty()
+        {
+            None
+        } else {
+            let keyframes = c.keyframes.iter()
+                .map(|k| Keyframe::from_matrix(k.time, &Mat4::look_at(place(k.look_from), place(k.look_at), vec3(k.up)))
+                    .ok_or_else(|| SceneError::Invalid(format!("camera keyframe at time {} does not face any direction", k.time))))
+                .collect::<Result<Vec<_>, SceneError>>()?;
+            Some(AnimatedTransform::new(keyframes))
+        };
+        if let Some(path) = &camera_path
+        {
+            camera.set_pose(&path.matrix(0.5 * (camera.shutter_open + camera.shutter_close)));
+        }
+
+        Ok(Scene { camera, world, lights, camera_path })
+    }
+
+    /// The camera for frame `frame` of an animation, counted from 1, with its shutter interval
+    /// moved to the frame's time. A moving camera is placed where it is halfway through the
+    /// exposure and stays there while the shutter is open.
+    pub fn frame_camera(&self, frame: u32) -> Camera
+    {
+        let mut camera = self.camera.clone();
+        let start = frame.saturating_sub(1) as f32;
+        camera.shutter_open += start;
+        camera.shutter_close += start;
+        if let Some(path) = &self.camera_path
+        {
+            camera.set_pose(&path.matrix(0.5 * (camera.shutter_open + camera.shutter_close)));
+        }
+        camera
+    }
+}
+
+fn vec3(v: [f32; 3]) -> Vec3
+{
+    Vec3::new(v[0], v[1], v[2])
+}
+
+// Resolves named textures and materials on first use, so each one is only constructed once and
+// shared between every object referring to it.
+struct SceneBuilder<'a>
+{
+    description: &'a SceneDescription,
+    directory: &'a Path,
+    textures: HashMap<String, Option<Arc<dyn Texture>>>,
+    materials: HashMap<String, Arc<dyn Material>>,
+}
+
+impl SceneBuilder<'_>
+{
+    fn path(&self, path: &str) -> PathBuf
+    {
+        self.directory.join(path)
+    }
+
+    fn texture(&mut self, reference: &TextureReference) -> Result<Arc<dyn Texture>, SceneError>
+    {
+        let name = match reference {
+            TextureReference::Color(c) => return Ok(Arc::new(SolidColor::new(c[0], c[1], c[2]))),
+            TextureReference::Named(name) => name,
+        };
+
+        match self.textures.get(name) {
+            Some(Some(texture)) => return Ok(texture.clone()),
+            Some(None) => return Err(SceneError::RecursiveTexture(name.clone())),
+            None => {}
+        }
+
+        let description = self.description.textures.get(name).ok_or_else(|| SceneError::UnknownTexture(name.clone()))?;
+        self.textures.insert(name.clone(), None);
+
+        let texture: Arc<dyn Texture> = match description {
+            TextureDescription::Solid { color } => Arc::new(SolidColor::new(color[0], color[1], color[2])),
+            TextureDescription::Checker { width, height, even, odd } => {
+                Arc::new(CheckerTexture::new(*width, *height, self.texture(even)?, self.texture(odd)?))
+            }
+            TextureDescription::Image { path } => {
+                let path = self.path(path).to_string_lossy().into_owned();
+                Arc::new(ImageTexture::new(&path).map_err(|error| SceneError::Texture(path, error))?)
+            }
+            TextureDescription::Noise { scale } => Arc::new(NoiseTexture::new(*scale)),
+        };
+
+        self.textures.insert(name.clone(), Some(texture.clone()));
+        Ok(texture)
+    }
+
+    fn material(&mut self, reference: &MaterialReference) -> Result<Arc<dyn Material>, SceneError>
+    {
+        let name = match reference {
+            MaterialReference::Inline(description) => return self.build_material(description),
+            MaterialReference::Named(name) => name,
+        };
+
+        if let Some(material) = self.materials.get(name)
+        {
+            return Ok(material.clone());
+        }
+
+        let description = self.description.materials.get(name).ok_or_else(|| SceneError::UnknownMaterial(name.clone()))?;
+        let material = self.build_material(description)?;
+        self.materials.insert(name.clone(), material.clone());
+        Ok(material)
+    }
+
+    fn build_material(&mut self, description: &MaterialDescription) -> Result<Arc<dyn Material>, SceneError>
+    {
+        Ok(match description {
+            MaterialDescription::Lambertian { texture } => Arc::new(Lambertian::new(self.texture(texture)?)),
+            MaterialDescription::Metal { albedo, fuzz } => Arc::new(Metal::new(vec3(*albedo), *fuzz)),
+            MaterialDescription::Dielectric { refraction_index } => Arc::new(Dielectric::new(*refraction_index)),
+            MaterialDescription::DiffuseLight { texture } => Arc::new(DiffuseMaterial::new(self.texture(texture)?)),
+            MaterialDescription::Isotropic { texture } => Arc::new(Isotropic::new(self.texture(texture)?)),
+            MaterialDescription::HenyeyGreenstein { texture, g } => {
+                if !g.is_finite() || g.abs() >= 1.0
+                {
+                    return Err(SceneError::Invalid(format!("henyey_greenstein g must lie strictly between -1 and 1, got {g}")));
+                }
+                Arc::new(HenyeyGreenstein::new(self.texture(texture)?, *g))
+            }
+        })
+    }
+
+    fn is_light(&self, object: &ObjectDescription) -> bool
+    {
+        let reference = match object {
+            ObjectDescription::Sphere { material, .. } => material,
+            ObjectDescription::Quad { material, .. } => material,
+            ObjectDescription::Triangle { material, .. } => material,
+            ObjectDescription::Box { material, .. } => material,
+            ObjectDescription::Disk { material, .. } => material,
+            ObjectDescription::Annulus { material, .. } => material,
+            ObjectDescription::Cylinder { .. }
+            | ObjectDescription::Cone { .. }
+            | ObjectDescription::Torus { .. }
+            | ObjectDescription::Plane { .. }
+            | ObjectDescription::Sdf { .. }
+            | ObjectDescription::Union { .. }
+            | ObjectDescription::Intersection { .. }
+            | ObjectDescription::Difference { .. }
+            | ObjectDescription::Mesh { .. }
+            | ObjectDescription::RandomSpheres { .. }
+            | ObjectDescription::ConstantMedium { .. }
+            | ObjectDescription::HeterogeneousMedium { .. } => return false,
+            ObjectDescription::Instance { object, .. } | ObjectDescription::Animated { object, .. } => return self.is_light(object),
+        };
+
+        let description = match reference {
+            MaterialReference::Named(name) => self.description.materials.get(name),
+            MaterialReference::Inline(description) => Some(description),
+        };
+        matches!(description, Some(MaterialDescription::DiffuseLight { .. }))
+    }
+
+    fn density(&self, description: &DensityDescription) -> Result<Arc<dyn DensityField>, SceneError>
+    {
+        Ok(match description {
+            DensityDescription::Noise { scale, density, depth } => {
+                if *density <= 0.0
+                {
+                    return Err(SceneError::Invalid(format!("noise density must be positive, got {density}")));
+                }
+                Arc::new(NoiseDensity::new(*scale, *density, *depth))
+            }
+            DensityDescription::VoxelGrid { path, scale } => {
+                if *scale <= 0.0
+                {
+                    return Err(SceneError::Invalid(format!("voxel grid scale must be positive, got {scale}")));
+                }
+                let path = self.path(path).to_string_lossy().into_owned();
+                let grid = VoxelGrid::load(&path).map_err(|error| SceneError::VoxelGrid(path, error))?;
+                Arc::new(grid.scaled(*scale))
+            }
+        })
+    }
+
+    fn sdf(description: &SdfDescription) -> Arc<dyn Sdf>
+    {
+        match description {
+            SdfDescription::Sphere { center, radius } => Arc::new(SdfSphere::new(vec3(*center), *radius)),
+            SdfDescription::RoundedBox { center, size, radius } => Arc::new(RoundedBox::new(vec3(*center), vec3(*size), *radius)),
+            SdfDescription::Mandelbulb { center, scale, power, iterations } => {
+                Arc::new(Mandelbulb::new(vec3(*center), *scale, *power, *iterations))
+            }
+            SdfDescription::SmoothUnion { a, b, smoothness } => {
+                Arc::new(SmoothUnion::new(SceneBuilder::sdf(a), SceneBuilder::sdf(b), *smoothness))
+            }
+        }
+    }
+
+    fn object(&mut self, description: &ObjectDescription) -> Result<Box<dyn Hittable>, SceneError>
+    {
+        Ok(match description {
+            ObjectDescription::Sphere { center, center2: None, radius, material } => {
+                Box::new(Sphere::new(vec3(*center), *radius, self.material(material)?))
+            }
+            ObjectDescription::Sphere { center, center2: Some(center2), radius, material } => {
+                Box::new(Sphere::new_moving(vec3(*center), vec3(*center2), *radius, self.material(material)?))
+            }
+            ObjectDescription::Quad { q, u, v, material } => {
+                Box::new(Quad::new(vec3(*q), vec3(*u), vec3(*v), self.material(material)?))
+            }
+            ObjectDescription::Triangle { vertices, normals, uvs, material } => {
+                let mut triangle = Triangle::new(vec3(vertices[0]), vec3(vertices[1]), vec3(vertices[2]), self.material(material)?);
+                if let Some(normals) = normals
+                {
+                    triangle = triangle.with_normals(normals.map(vec3));
+                }
+                if let Some(uvs) = uvs
+                {
+                    triangle = triangle.with_uvs(*uvs);
+                }
+                Box::new(triangle)
+            }
+            ObjectDescription::Box { min, max, material } => {
+                Box::new(BoxShape::new(vec3(*min), vec3(*max), self.material(material)?))
+            }
+            ObjectDescription::Cylinder { base, top, radius, capped, material } => {
+                let height = (vec3(*top) - vec3(*base)).length();
+                if height <= 0.0 || *radius <= 0.0
+                {
+                    return Err(SceneError::Invalid(format!("cylinder height and radius must be positive, got {height} and {radius}")));
+                }
+                Box::new(Cylinder::new(vec3(*base), vec3(*top), *radius, *capped, self.material(material)?))
+            }
+            ObjectDescription::Cone { base, apex, radius, capped, material } => {
+                let height = (vec3(*apex) - vec3(*base)).length();
+                if height <= 0.0 || *radius <= 0.0
+                {
+                    return Err(SceneError::Invalid(format!("cone height and radius must be positive, got {height} and {radius}")));
+                }
+                Box::new(Cone::new(vec3(*base), vec3(*apex), *radius, *capped, self.material(material)?))
+            }
+            ObjectDescription::Disk { center, normal, radius, material } => {
+                if *radius <= 0.0
+                {
+                    return Err(SceneError::Invalid(format!("disk radius must be positive, got {radius}")));
+                }
+                Box::new(Disk::new(vec3(*center), vec3(*normal), *radius, self.material(material)?))
+            }
+            ObjectDescription::Annulus { center, normal, inner_radius, outer_radius, material } => {
+                if *inner_radius < 0.0 || inner_radius >= outer_radius
+                {
+                    return Err(SceneError::Invalid(format!("annulus radii must satisfy 0 <= inner < outer, got {inner_radius} and {outer_radius}")));
+                }
+                Box::new(Annulus::new(vec3(*center), vec3(*normal), *inner_radius, *outer_radius, self.material(material)?))
+            }
+            ObjectDescription::Torus { center, axis, major_radius, minor_radius, material } => {
+                Box::new(Torus::new(vec3(*center), vec3(*axis), *major_radius, *minor_radius, self.material(material)?))
+            }
+            ObjectDescription::Plane { point, normal, tile_size, material } => {
+                if *tile_size <= 0.0
+                {
+                    return Err(SceneError::Invalid(format!("plane tile size must be positive, got {tile_size}")));
+                }
+                Box::new(Plane::new(vec3(*point), vec3(*normal), *tile_size, self.material(material)?))
+            }
+            ObjectDescription::Sdf { shape, material } => {
+                Box::new(SdfHittable::new(SceneBuilder::sdf(shape), self.material(material)?))
+            }
+            ObjectDescription::Mesh { path } => {
+                let path = self.path(path).to_string_lossy().into_owned();
+                Box::new(Mesh::load_obj(&path).map_err(|error| SceneError::Mesh(path, error))?)
+            }
+            ObjectDescription::RandomSpheres { seed } => {
+                let mut rng = seed.map_or_else(StdRng::from_entropy, StdRng::seed_from_u64);
+                Box::new(FlatBvh::new(random_spheres(&mut rng)))
+            }
+            ObjectDescription::ConstantMedium { boundary, density, material } => {
+                if *density <= 0.0
+                {
+                    return Err(SceneError::Invalid(format!("constant medium density must be positive, got {density}")));
+                }
+                let boundary: Arc<dyn Hittable> = Arc::from(self.object(boundary)?);
+                Box::new(ConstantMedium::new(boundary, *density, self.material(material)?))
+            }
+            ObjectDescription::HeterogeneousMedium { boundary, density, material } => {
+                let boundary: Arc<dyn Hittable> = Arc::from(self.object(boundary)?);
+                Box::new(HeterogeneousMedium::new(boundary, self.density(density)?, self.material(material)?))
+            }
+            ObjectDescription::Instance { object, transform } => {
+                let matrix = TransformDescription::compose(transform)?;
+                Box::new(Instance::new(Arc::from(self.object(object)?), matrix).ok_or(SceneError::SingularTransform)?)
+            }
+            ObjectDescription::Animated { object, keyframes } => {
+                if keyframes.is_empty()
+                {
+                    return Err(SceneError::NoKeyframes);
+                }
+                let keyframes = keyframes.iter()
+                    .map(|k| Keyframe::from_matrix(k.time, &TransformDescription::compose(&k.transform)?).ok_or(SceneError::ShearedKeyframe(k.time)))
+                    .collect::<Result<Vec<_>, SceneError>>()?;
+                Box::new(AnimatedInstance::new(Arc::from(self.object(object)?), AnimatedTransform::new(keyframes)))
+            }
+            ObjectDescription::Union { a, b } => self.csg(CsgOperation::Union, a, b)?,
+            ObjectDescription::Intersection { a, b } => self.csg(CsgOperation::Intersection, a, b)?,
+            ObjectDescription::Difference { a, b } => self.csg(CsgOperation::Difference, a, b)?,
+        })
+    }
+
+    fn csg(&mut self, operation: CsgOperation, a: &ObjectDescription, b: &ObjectDescription) -> Result<Box<dyn Hittable>, SceneError>
+    {
+        let a: Arc<dyn Hittable> = Arc::from(self.object(a)?);
+        let b: Arc<dyn Hittable> = Arc::from(self.object(b)?);
+        Ok(Box::new(Csg::new(operation, a, b)))
+    }
+}
+
+// Small spheres jittered within the cells of a grid, leaving room for a large sphere at x = 4.
+// Most are diffuse, some metal and a few glass.
+fn random_spheres(rng: &mut StdRng) -> HittableList
+{
+    let mut spheres = HittableList::new(vec![]);
+
+    for a in -11..11 {
+        for b in -11..11 {
+            let choose_material = rng.gen::<f32>();
+            let center = Vec3::new(a as f32 + 0.9 * rng.gen::<f32>(), 0.2, b as f32 + 0.9 * rng.gen::<f32>());
+            if (center - Vec3::new(4.0, 0.2, 0.0)).length() <= 0.9
+            {
+                continue;
+            }
+
+            let material: Arc<dyn Material> = if choose_material < 0.8
+            {
+                let color = Color::new(rng.gen(), rng.gen(), rng.gen()) * Color::new(rng.gen(), rng.gen(), rng.gen());
+                Arc::new(Lambertian::new(SolidColor::new(color.x(), color.y(), color.z())))
+            } else if choose_material < 0.95
+            {
+                let albedo = Color::new(rng.gen_range(0.5..1.0), rng.gen_range(0.5..1.0), rng.gen_range(0.5..1.0));
+                Arc::new(Metal::new(albedo, rng.gen_range(0.0..0.5)))
+            } else {
+                Arc::new(Dielectric::new(1.5))
+            };
+            spheres.objects.push(Box::new(Sphere::new(center, 0.2, material)));
+        }
+    }
+
+    spheres
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    const CAMERA: &str = r#""camera": {"look_from": [0, 0, 5], "look_at": [0, 0, 0], "vfov": 40, "aspect_ratio": 1.0, "image_width": 10, "samples_per_pixel": 1, "max_depth": 2}"#;
+
+    // Parses a scene with a small camera and the given top level fields besides it.
+    fn parse(fields: &str) -> Result<Scene, SceneError>
+    {
+        Scene::parse(&format!("{{{CAMERA}, {fields}}}"), Path::new(""))
+    }
+
+    fn assert_invalid(fields: &str)
+    {
+        assert!(matches!(parse(fields), Err(SceneError::Invalid(_))), "{fields} was accepted");
+    }
+
+    #[test]
+    fn shipped_scene_builds()
+    {
+        let scene = match Scene::parse(include_str!("../scenes/cornell_box.json"), Path::new("")) {
+            Ok(scene) => scene,
+            Err(error) => panic!("{error}"),
+        };
+        assert!(!scene.world.objects.is_empty());
+        assert_eq!(scene.lights.objects.len(), 1);
+    }
+
+    #[test]
+    fn shutter_must_close_after_opening_and_rolling_exposure_must_fit()
+    {
+        let scene = |camera: &str| {
+            let source = format!(r#"{{"camera": {{"look_from": [0, 0, 5], "look_at": [0, 0, 0], "vfov": 40, "aspect_ratio": 1.0, "image_width": 10,
+                "samples_per_pixel": 1, "max_depth": 2, {camera}}}, "objects": []}}"#);
+            Scene::parse(&source, Path::new(""))
+        };
+        let invalid = |camera: &str| assert!(matches!(scene(camera), Err(SceneError::Invalid(_))), "{camera} was accepted");
+
+        invalid(r#""shutter_open": 1, "shutter_close": 0.5"#);
+        invalid(r#""shutter_curve": {"type": "rolling", "exposure": 0}"#);
+        invalid(r#""shutter_curve": {"type": "rolling", "exposure": 1.5}"#);
+        assert!(scene(r#""shutter_open": 0.5, "shutter_close": 0.5, "shutter_curve": {"type": "rolling", "exposure": 1}"#).is_ok());
+    }
+
+    #[test]
+    fn unknown_references_are_reported()
+    {
+        let result = parse(r#""objects": [{"type": "sphere", "center": [0, 0, 0], "radius": 1, "material": "missing"}]"#);
+        assert!(matches!(result, Err(SceneError::UnknownMaterial(name)) if name == "missing"));
+
+        let result = parse(r#""objects": [{"type": "sphere", "center": [0, 0, 0], "radius": 1, "material": {"type": "lambertian", "texture": "missing"}}]"#);
+        assert!(matches!(result, Err(SceneError::UnknownTexture(name)) if name == "missing"));
+    }
+
+    #[test]
+    fn missing_image_is_reported()
+    {
+        let result = parse(r#""textures": {"earth": {"type": "image", "path": "no_such_image.png"}},
+            "objects": [{"type": "sphere", "center": [0, 0, 0], "radius": 1, "material": {"type": "lambertian", "texture": "earth"}}]"#);
+        assert!(matches!(result, Err(SceneError::Texture(path, _)) if path == "no_such_image.png"));
+    }
+
+    #[test]
+    fn invalid_primitives_are_rejected()
+    {
+        assert_invalid(r#""objects": [{"type": "cone", "base": [0, 0, 0], "apex": [0, 0, 0], "radius": 1, "material": {"type": "lambertian", "texture": [1, 1, 1]}}]"#);
+        assert_invalid(r#""objects": [{"type": "cylinder", "base": [0, 0, 0], "top": [0, 1, 0], "radius": 0, "material": {"type": "lambertian", "texture": [1, 1, 1]}}]"#);
+        assert_invalid(r#""objects": [{"type": "disk", "center": [0, 0, 0], "normal": [0, 1, 0], "radius": -1, "material": {"type": "diffuse_light", "texture": [1, 1, 1]}}]"#);
+        assert_invalid(r#""objects": [{"type": "annulus", "center": [0, 0, 0], "normal": [0, 1, 0], "inner_radius": 2, "outer_radius": 1,
+            "material": {"type": "diffuse_light", "texture": [1, 1, 1]}}]"#);
+        assert_invalid(r#""objects": [{"type": "plane", "point": [0, 0, 0], "normal": [0, 1, 0], "tile_size": 0, "material": {"type": "lambertian", "texture": [1, 1, 1]}}]"#);
+        assert_invalid(r#""objects": [{"type": "constant_medium", "boundary": {"type": "sphere", "center": [0, 0, 0], "radius": 1, "material": {"type": "dielectric", "refraction_index": 1.5}},
+            "density": 0, "material": {"type": "isotropic", "texture": [1, 1, 1]}}]"#);
+    }
+
+    #[test]
+    fn negative_media_densities_are_rejected()
+    {
+        let medium = |density: &str| format!(r#""objects": [{{"type": "heterogeneous_medium", "boundary": {{"type": "box", "min": [0, 0, 0], "max": [1, 1, 1],
+            "material": {{"type": "dielectric", "refraction_index": 1.5}}}}, "density": {density}, "material": {{"type": "isotropic", "texture": [1, 1, 1]}}}}]"#);
+        assert_invalid(&medium(r#"{"type": "noise", "scale": 4, "density": -1}"#));
+        assert_invalid(&medium(r#"{"type": "voxel_grid", "path": "missing.vol", "scale": -2}"#));
+        assert!(parse(&medium(r#"{"type": "noise", "scale": 4, "density": 1}"#)).is_ok());
+    }
+
+    #[test]
+    fn phase_function_asymmetry_must_lie_inside_the_unit_interval()
+    {
+        let sphere = |g: f32| format!(r#""objects": [{{"type": "sphere", "center": [0, 0, 0], "radius": 1,
+            "material": {{"type": "henyey_greenstein", "texture": [1, 1, 1], "g": {g}}}}}]"#);
+        assert_invalid(&sphere(1.0));
+        assert_invalid(&sphere(-1.5));
+        assert!(parse(&sphere(-0.9)).is_ok());
+    }
+}
@@ -0,0 +1,253 @@
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::sphere::get_sphere_uv;
+use crate::vec3::Vec3;
+
+/// Shape given by a signed distance function: negative inside, positive outside, and never
+/// larger than the true distance to the surface, so a sphere of that radius is always empty.
+pub trait Sdf: Sync + Send
+{
+    fn distance(&self, point: &Vec3) -> f32;
+    /// Box containing every point where `distance` is not positive.
+    fn bounding_box(&self) -> Aabb;
+}
+
+impl<S: Sdf + ?Sized> Sdf for Arc<S>
+{
+    fn distance(&self, point: &Vec3) -> f32 {
+        (**self).distance(point)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        (**self).bounding_box()
+    }
+}
+
+pub struct SdfSphere
+{
+    center: Vec3,
+    radius: f32,
+}
+
+impl SdfSphere
+{
+    pub fn new(center: Vec3, radius: f32) -> SdfSphere
+    {
+        SdfSphere { center, radius }
+    }
+}
+
+impl Sdf for SdfSphere
+{
+    fn distance(&self, point: &Vec3) -> f32 {
+        (*point - self.center).length() - self.radius
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let r = Vec3::new(self.radius, self.radius, self.radius);
+        Aabb::new(self.center - r, self.center + r)
+    }
+}
+
+/// Axis-aligned box of `size` whose edges and corners are rounded off with `radius`.
+pub struct RoundedBox
+{
+    center: Vec3,
+    half_size: Vec3,
+    radius: f32,
+}
+
+impl RoundedBox
+{
+    pub fn new(center: Vec3, size: Vec3, radius: f32) -> RoundedBox
+    {
+        RoundedBox { center, half_size: 0.5 * size, radius }
+    }
+}
+
+impl Sdf for RoundedBox
+{
+    fn distance(&self, point: &Vec3) -> f32 {
+        let p = *point - self.center;
+        let q = Vec3::new(
+            p.x().abs() - self.half_size.x() + self.radius,
+            p.y().abs() - self.half_size.y() + self.radius,
+            p.z().abs() - self.half_size.z() + self.radius,
+        );
+        let outside = Vec3::new(q.x().max(0.0), q.y().max(0.0), q.z().max(0.0)).length();
+        let inside = q.x().max(q.y()).max(q.z()).min(0.0);
+        outside + inside - self.radius
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        Aabb::new(self.center - self.half_size, self.center + self.half_size)
+    }
+}
+
+/// The Mandelbulb fractal of the given `power`, scaled to fit a sphere of about `scale` around
+/// `center`. More `iterations` give finer detail at the cost of slower tracing.
+pub struct Mandelbulb
+{
+    center: Vec3,
+    scale: f32,
+    power: f32,
+    iterations: u32,
+}
+
+impl Mandelbulb
+{
+    pub fn new(center: Vec3, scale: f32, power: f32, iterations: u32) -> Mandelbulb
+    {
+        Mandelbulb { center, scale, power, iterations }
+    }
+}
+
+impl Sdf for Mandelbulb
+{
+    // Distance estimate from the running derivative of z ↦ zⁿ + c in spherical coordinates.
+    fn distance(&self, point: &Vec3) -> f32 {
+        let c = (*point - self.center) / self.scale;
+        let mut z = c;
+        let mut dr = 1.0;
+        let mut r = z.length();
+
+        for _ in 0..self.iterations {
+            if r > 2.0 || r == 0.0
+            {
+                break;
+            }
+
+            let theta = (z.z() / r).acos() * self.power;
+            let phi = z.y().atan2(z.x()) * self.power;
+            dr = r.powf(self.power - 1.0) * self.power * dr + 1.0;
+
+            let zr = r.powf(self.power);
+            z = zr * Vec3::new(theta.sin() * phi.cos(), theta.sin() * phi.sin(), theta.cos()) + c;
+            r = z.length();
+        }
+
+        if r == 0.0
+        {
+            return 0.0;
+        }
+        0.5 * r.ln() * r / dr * self.scale
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let r = 1.25 * Vec3::new(self.scale, self.scale, self.scale);
+        Aabb::new(self.center - r, self.center + r)
+    }
+}
+
+/// Union of two shapes blended together wherever they come closer than `smoothness`.
+pub struct SmoothUnion<A: Sdf, B: Sdf>
+{
+    a: A,
+    b: B,
+    smoothness: f32,
+}
+
+impl<A: Sdf, B: Sdf> SmoothUnion<A, B>
+{
+    pub fn new(a: A, b: B, smoothness: f32) -> SmoothUnion<A, B>
+    {
+        SmoothUnion { a, b, smoothness }
+    }
+}
+
+impl<A: Sdf, B: Sdf> Sdf for SmoothUnion<A, B>
+{
+    // Polynomial smooth minimum, which lowers the plain minimum by at most smoothness / 4.
+    fn distance(&self, point: &Vec3) -> f32 {
+        let (a, b) = (self.a.distance(point), self.b.distance(point));
+        if self.smoothness <= 0.0
+        {
+            return a.min(b);
+        }
+        let h = (self.smoothness - (a - b).abs()).max(0.0) / self.smoothness;
+        a.min(b) - 0.25 * h * h * self.smoothness
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let bbox = Aabb::surrounding(&self.a.bounding_box(), &self.b.bounding_box());
+        let pad = Vec3::new(1.0, 1.0, 1.0) * (0.25 * self.smoothness.max(0.0));
+        Aabb::new(bbox.min - pad, bbox.max + pad)
+    }
+}
+
+const MAX_STEPS: usize = 512;
+// Rays stop once they come this close to the surface, relative to the distance they travelled.
+const HIT_EPSILON: f32 = 1e-4;
+
+/// Renders a signed distance function by sphere tracing: the ray advances by the distance to the
+/// surface until it is close enough to count as a hit. Normals are the gradient of the distance.
+pub struct SdfHittable<S: Sdf, T: Material>
+{
+    sdf: S,
+    material: T,
+    bbox: Aabb,
+}
+
+impl<S: Sdf, T: Material> SdfHittable<S, T>
+{
+    pub fn new(sdf: S, material: T) -> SdfHittable<S, T>
+    {
+        let bbox = sdf.bounding_box();
+        SdfHittable { sdf, material, bbox }
+    }
+
+    // Central differences on a tetrahedron need four evaluations instead of six.
+    fn normal(&self, point: Vec3, h: f32) -> Vec3
+    {
+        let offsets = [Vec3::new(1.0, -1.0, -1.0), Vec3::new(-1.0, -1.0, 1.0), Vec3::new(-1.0, 1.0, -1.0), Vec3::new(1.0, 1.0, 1.0)];
+        let gradient = offsets.iter().fold(Vec3::new_zero(), |gradient, &k| gradient + k * self.sdf.distance(&(point + h * k)));
+        gradient.normalize()
+    }
+}
+
+impl<S: Sdf, T: Material> Hittable for SdfHittable<S, T> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        let (start, end) = self.bbox.interval(ray, t_min, t_max)?;
+        let length = ray.direction.length();
+        let epsilon = |t: f32| HIT_EPSILON * (1.0 + t * length);
+
+        // A ray leaving the surface, as after a bounce, starts within epsilon of it and must not
+        // hit it again right away, so hits only count once the ray has moved clear of it.
+        let mut leaving = self.sdf.distance(&ray.at(start)).abs() < epsilon(start);
+        let mut t = start;
+
+        for _ in 0..MAX_STEPS {
+            let distance = self.sdf.distance(&ray.at(t)).abs();
+            let epsilon = epsilon(t);
+
+            if distance >= epsilon
+            {
+                leaving = false;
+            } else if !leaving
+            {
+                let point = ray.at(t);
+                let normal = self.normal(point, epsilon);
+                let (u, v) = get_sphere_uv(&normal);
+                let mut hit_record = HitRecord { point, normal, t, material: &self.material, front_face: false, u, v };
+                hit_record.set_normal(ray);
+                return Some(hit_record);
+            }
+
+            t += distance.max(epsilon) / length;
+            if t > end
+            {
+                return None;
+            }
+        }
+
+        None
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+}
@@ -1,9 +1,13 @@
+use rand::Rng;
+
+use crate::aabb::Aabb;
 use crate::hittable::{HitRecord, Hittable};
 use crate::material::Material;
+use crate::onb::Onb;
 use crate::ray::Ray;
 use crate::vec3::Vec3;
 
-fn get_sphere_uv(point: &Vec3) -> (f32, f32)
+pub(crate) fn get_sphere_uv(point: &Vec3) -> (f32, f32)
 {
     let theta = point.x().atan2(point.z());
     let radius = point.length();
@@ -17,6 +21,7 @@ fn get_sphere_uv(point: &Vec3) -> (f32, f32)
     (u, v)
 }
 
+/// Sphere, optionally moving linearly from one center at time 0 to another at time 1.
 pub struct Sphere<T: Material>
 {
     center_vec: Vec3,
@@ -24,23 +29,35 @@ pub struct Sphere<T: Material>
     radius: f32,
     material: T,
     is_moving: bool,
+    bbox: Aabb,
 }
 
 impl<T: Material> Sphere<T> {
     pub fn new(center: Vec3, radius: f32, material: T) -> Self
     {
-        Sphere { center_vec: center, center1: center, radius, material, is_moving: false }
+        let radius_vec = Vec3::new(radius, radius, radius);
+        let bbox = Aabb::new(center - radius_vec, center + radius_vec);
+        Sphere { center_vec: center, center1: center, radius, material, is_moving: false, bbox }
     }
-    pub fn new_moving(center1: Vec3, center2: Vec3, radius: f32, material: T) -> Self { Sphere {center_vec: center2 - center1, center1, radius, material, is_moving: true} }
 
+    pub fn new_moving(center1: Vec3, center2: Vec3, radius: f32, material: T) -> Self
+    {
+        let radius_vec = Vec3::new(radius, radius, radius);
+        let box1 = Aabb::new(center1 - radius_vec, center1 + radius_vec);
+        let box2 = Aabb::new(center2 - radius_vec, center2 + radius_vec);
+        Sphere {center_vec: center2 - center1, center1, radius, material, is_moving: true, bbox: Aabb::surrounding(&box1, &box2)}
+    }
+
+    /// Center at `time`. Outside of `0..1` the sphere stays at the nearer end, which keeps it
+    /// inside its bounding box during later frames of an animation.
     pub fn sphere_center(&self, time: f32) -> Vec3
     {
-        return self.center1 + (time * self.center_vec);
+        self.center1 + (time.clamp(0.0, 1.0) * self.center_vec)
     }
 }
 
 impl<T: Material> Hittable for Sphere<T> {
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
         let center = if self.is_moving { self.sphere_center(ray.time) } else { self.center1 };
         let oc = center - ray.origin;
 
@@ -69,4 +86,40 @@ impl<T: Material> Hittable for Sphere<T> {
         hit.set_normal(ray);
         Some(hit)
     }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    // Directions are sampled uniformly within the cone the sphere subtends as seen from `origin`.
+    fn pdf(&self, origin: &Vec3, direction: &Vec3, time: f32) -> f32 {
+        if self.hit(&Ray::new(*origin, *direction, time), 0.001, f32::INFINITY).is_none()
+        {
+            return 0.0;
+        }
+
+        let center = if self.is_moving { self.sphere_center(time) } else { self.center1 };
+        let distance_squared = (center - *origin).length_squared();
+        if distance_squared <= self.radius * self.radius
+        {
+            return 0.0;
+        }
+
+        let cos_theta_max = (1.0 - self.radius * self.radius / distance_squared).sqrt();
+        1.0 / (std::f32::consts::TAU * (1.0 - cos_theta_max))
+    }
+
+    fn sample(&self, origin: &Vec3, time: f32) -> Vec3 {
+        let center = if self.is_moving { self.sphere_center(time) } else { self.center1 };
+        let direction = center - *origin;
+        let distance_squared = direction.length_squared();
+        let mut rng = rand::thread_rng();
+
+        let cos_theta_max = (1.0 - (self.radius * self.radius / distance_squared).min(1.0)).sqrt();
+        let z = 1.0 + rng.gen::<f32>() * (cos_theta_max - 1.0);
+        let phi = std::f32::consts::TAU * rng.gen::<f32>();
+        let sin_theta = (1.0 - z * z).sqrt();
+
+        Onb::new(&direction).transform(Vec3::new(phi.cos() * sin_theta, phi.sin() * sin_theta, z))
+    }
 }
\ No newline at end of file
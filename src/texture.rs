@@ -1,12 +1,22 @@
+use std::sync::Arc;
+
 use crate::color::Color;
 use crate::perlin::Perlin;
 use crate::vec3::Vec3;
 
-pub trait Texture: Sync
+/// Color lookup by surface coordinates `u`, `v` and hit point `p`.
+pub trait Texture: Sync + Send
 {
     fn value(&self, u: f32, v: f32, p: &Vec3) -> Vec3;
 }
 
+impl<T: Texture + ?Sized> Texture for Arc<T>
+{
+    fn value(&self, u: f32, v: f32, p: &Vec3) -> Vec3 {
+        (**self).value(u, v, p)
+    }
+}
+
 #[derive(Copy, Clone)]
 pub struct SolidColor
 {
@@ -21,10 +31,11 @@ impl SolidColor
 impl Texture for SolidColor
 {
     fn value(&self, _: f32, _: f32, _: &Vec3) -> Vec3 {
-        return self.albedo;
+        self.albedo
     }
 }
 
+/// Alternates two textures in a `width` by `height` checkerboard over the surface coordinates.
 #[derive(Clone, Copy)]
 pub struct CheckerTexture<T: Texture, U: Texture>
 {
@@ -55,6 +66,7 @@ impl<T: Texture, U: Texture> Texture for CheckerTexture<T, U>
     }
 }
 
+/// Texture read from an image file.
 pub struct ImageTexture
 {
     data: Vec<u8>,
@@ -62,26 +74,27 @@ pub struct ImageTexture
 }
 
 impl ImageTexture {
-    pub fn new(path: &str) -> ImageTexture
+    pub fn new(path: &str) -> Result<ImageTexture, image::ImageError>
     {
-        let image = image::open(path).expect("Image not found").to_rgb8();
+        let image = image::open(path)?.to_rgb8();
         let (x, y) = image.dimensions();
-        ImageTexture {data: image.into_raw(), dimensions: (x, y)}
+        Ok(ImageTexture {data: image.into_raw(), dimensions: (x, y)})
     }
 }
 
 impl Texture for ImageTexture {
     fn value(&self, u: f32, v: f32, _: &Vec3) -> Vec3 {
-        let u = if u < 0.0 { 0.0 } else if u > 1.0 {1.0} else { u };
-        let v = 1.0 - if v < 0.0 { 0.0 } else if v > 1.0 {1.0} else { v };
+        let u = u.clamp(0.0, 1.0);
+        let v = 1.0 - v.clamp(0.0, 1.0);
 
         let i = (u * self.dimensions.0 as f32) as usize;
         let j = (v * self.dimensions.1 as f32) as usize;
         let idx = 3 * i + j * 3 * self.dimensions.0 as usize;
-        return Color::new(self.data[idx] as f32 / 255.0, self.data[idx + 1] as f32 / 255.0, self.data[idx + 2] as f32 / 255.0,);
+        Color::new(self.data[idx] as f32 / 255.0, self.data[idx + 1] as f32 / 255.0, self.data[idx + 2] as f32 / 255.0,)
     }
 }
 
+/// Marble-like texture built from Perlin turbulence.
 #[derive(Clone)]
 pub struct NoiseTexture
 {
@@ -0,0 +1,166 @@
+use crate::aabb::Aabb;
+use crate::disk::circle_bbox;
+use crate::hittable::{HitRecord, Hittable};
+use crate::material::Material;
+use crate::onb::Onb;
+use crate::polynomial::solve_quartic;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+
+/// Ring shaped surface swept by a circle of `minor_radius` whose center travels around `axis` at
+/// `major_radius` from `center`. `u` runs around the axis and `v` around the tube.
+pub struct Torus<T: Material>
+{
+    center: Vec3,
+    onb: Onb,
+    major_radius: f32,
+    minor_radius: f32,
+    material: T,
+    bbox: Aabb,
+}
+
+impl<T: Material> Torus<T>
+{
+    pub fn new(center: Vec3, axis: Vec3, major_radius: f32, minor_radius: f32, material: T) -> Torus<T>
+    {
+        let ring = circle_bbox(center, axis, major_radius);
+        let tube = Vec3::new(minor_radius, minor_radius, minor_radius);
+        let bbox = Aabb::new(ring.min - tube, ring.max + tube);
+        Torus { center, onb: Onb::new(&axis), major_radius, minor_radius, material, bbox }
+    }
+}
+
+impl<T: Material> Hittable for Torus<T> {
+    // Points on the surface satisfy (|p|² + R² - r²)² = 4R²(x² + y²) in the local frame, a quartic
+    // in the ray parameter. It is solved in double precision with a unit direction, starting
+    // from where the ray enters the bounding sphere so the coefficients stay small.
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        let local = |v: Vec3| {
+            let v = self.onb.to_local(v);
+            [v.x() as f64, v.y() as f64, v.z() as f64]
+        };
+        let dot = |a: [f64; 3], b: [f64; 3]| a[0] * b[0] + a[1] * b[1] + a[2] * b[2];
+
+        let length = ray.direction.length() as f64;
+        let direction = local(ray.direction).map(|d| d / length);
+        let mut origin = local(ray.origin - self.center);
+
+        let (major, minor) = (self.major_radius as f64, self.minor_radius as f64);
+        let bound = major + minor;
+        let k = dot(origin, direction);
+        let discriminant = k * k - (dot(origin, origin) - bound * bound);
+        if discriminant < 0.0
+        {
+            return None;
+        }
+        let shift = (-k - discriminant.sqrt()).max(0.0);
+        for (o, d) in origin.iter_mut().zip(direction) {
+            *o += shift * d;
+        }
+
+        let k = dot(origin, direction);
+        let m = dot(origin, origin) + major * major - minor * minor;
+        let r2 = 4.0 * major * major;
+        let roots = solve_quartic(
+            1.0,
+            4.0 * k,
+            4.0 * k * k + 2.0 * m - r2 * (direction[0] * direction[0] + direction[1] * direction[1]),
+            4.0 * k * m - 2.0 * r2 * (origin[0] * direction[0] + origin[1] * direction[1]),
+            m * m - r2 * (origin[0] * origin[0] + origin[1] * origin[1]),
+        );
+
+        let t = roots.as_slice().iter()
+            .map(|&s| ((s + shift) / length) as f32)
+            .find(|&t| t_min < t && t < t_max)?;
+
+        let point = self.onb.to_local(ray.at(t) - self.center);
+        let radial = Vec3::new(point.x(), point.y(), 0.0);
+        let radial_distance = radial.length();
+        let ring_point = if radial_distance > 0.0 { radial * (self.major_radius / radial_distance) } else { radial };
+
+        let mut hit_record = HitRecord {
+            point: ray.at(t),
+            normal: self.onb.transform((point - ring_point).normalize()),
+            t,
+            material: &self.material,
+            front_face: false,
+            u: point.y().atan2(point.x()) / std::f32::consts::TAU + 0.5,
+            v: point.z().atan2(radial_distance - self.major_radius) / std::f32::consts::TAU + 0.5,
+        };
+        hit_record.set_normal(ray);
+
+        Some(hit_record)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::material::Lambertian;
+    use crate::texture::SolidColor;
+
+    // Lies in the xz plane around the y axis, with a hole of radius 1.5 and an outer radius of 2.5.
+    fn torus() -> Torus<Lambertian<SolidColor>>
+    {
+        Torus::new(Vec3::new_zero(), Vec3::new(0.0, 1.0, 0.0), 2.0, 0.5, Lambertian::new(SolidColor::new(0.5, 0.5, 0.5)))
+    }
+
+    fn assert_hit(torus: &Torus<Lambertian<SolidColor>>, ray: &Ray, t_min: f32, t: f32, normal: Vec3, tolerance: f32)
+    {
+        let hit = torus.hit(ray, t_min, f32::INFINITY).expect("ray missed the torus");
+        assert!((hit.t - t).abs() < tolerance, "{} != {t}", hit.t);
+        assert!((hit.normal - normal).length() < tolerance, "{:?} != {normal:?}", hit.normal);
+    }
+
+    #[test]
+    fn ray_along_the_axis_passes_through_the_hole()
+    {
+        let ray = Ray::new(Vec3::new(0.0, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0), 0.0);
+        assert!(torus().hit(&ray, 0.001, f32::INFINITY).is_none());
+    }
+
+    #[test]
+    fn ray_across_the_hole_hits_both_sides_of_the_tube()
+    {
+        let torus = torus();
+        let ray = Ray::new(Vec3::new(-5.0, 0.0, 0.0), Vec3::new(2.0, 0.0, 0.0), 0.0);
+        assert_hit(&torus, &ray, 0.001, 1.25, Vec3::new(-1.0, 0.0, 0.0), 1e-4);
+        // Leaving the tube into the hole, with the normal turned against the ray.
+        assert_hit(&torus, &ray, 1.5, 1.75, Vec3::new(-1.0, 0.0, 0.0), 1e-4);
+        assert_hit(&torus, &ray, 2.0, 3.25, Vec3::new(-1.0, 0.0, 0.0), 1e-4);
+        assert_hit(&torus, &ray, 3.5, 3.75, Vec3::new(-1.0, 0.0, 0.0), 1e-4);
+        assert!(torus.hit(&ray, 4.0, f32::INFINITY).is_none());
+    }
+
+    #[test]
+    fn ray_from_the_center_hits_the_inside_of_the_ring()
+    {
+        let torus = torus();
+        let ray = Ray::new(Vec3::new_zero(), Vec3::new(0.0, 0.0, 1.0), 0.0);
+        let hit = torus.hit(&ray, 0.001, f32::INFINITY).unwrap();
+        assert!((hit.t - 1.5).abs() < 1e-4 && hit.front_face, "{}", hit.t);
+        assert!((hit.normal - Vec3::new(0.0, 0.0, -1.0)).length() < 1e-4);
+    }
+
+    #[test]
+    fn ray_tangent_to_the_top_of_the_tube()
+    {
+        let torus = torus();
+        // Touches the circle of radius 2 at height 0.5 where the ray crosses it, at x = ±2. The
+        // ray runs along the surface there, so the side the normal is turned to is arbitrary.
+        let ray = Ray::new(Vec3::new(-5.0, 0.5, 0.0), Vec3::new(1.0, 0.0, 0.0), 0.0);
+        for (t_min, t) in [(0.001, 3.0), (4.0, 7.0)] {
+            let hit = torus.hit(&ray, t_min, f32::INFINITY).expect("tangent ray missed the torus");
+            assert!((hit.t - t).abs() < 1e-2, "{} != {t}", hit.t);
+            assert!((hit.normal.y().abs() - 1.0).abs() < 1e-4, "{:?}", hit.normal);
+        }
+
+        let above = Ray::new(Vec3::new(-5.0, 0.51, 0.0), Vec3::new(1.0, 0.0, 0.0), 0.0);
+        assert!(torus.hit(&above, 0.001, f32::INFINITY).is_none());
+    }
+}
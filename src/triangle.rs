@@ -0,0 +1,144 @@
+use rand::Rng;
+
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+
+// Möller–Trumbore intersection, returning the ray parameter and the barycentric
+// coordinates of the second and third vertex.
+pub(crate) fn intersect_triangle(ray: &Ray, vertices: &[Vec3; 3], t_min: f32, t_max: f32) -> Option<(f32, f32, f32)>
+{
+    let edge1 = vertices[1] - vertices[0];
+    let edge2 = vertices[2] - vertices[0];
+
+    let p = ray.direction.cross(edge2);
+    let det = edge1.dot(p);
+
+    // Parallel rays give a determinant of zero up to rounding, which grows with the edges and the
+    // direction, so the threshold does as well.
+    let scale = edge1.length() * edge2.length() * ray.direction.length();
+    if det.abs() <= f32::EPSILON * scale { return None; }
+
+    let inv_det = 1.0 / det;
+    let s = ray.origin - vertices[0];
+    let b1 = s.dot(p) * inv_det;
+
+    if !(0.0..=1.0).contains(&b1)
+    {
+        return None;
+    }
+
+    let q = s.cross(edge1);
+    let b2 = ray.direction.dot(q) * inv_det;
+
+    if b2 < 0.0 || b1 + b2 > 1.0
+    {
+        return None;
+    }
+
+    let t = edge2.dot(q) * inv_det;
+
+    if t < t_min || t > t_max
+    {
+        return None;
+    }
+
+    Some((t, b1, b2))
+}
+
+pub(crate) fn interpolate<V: Copy + std::ops::Mul<f32, Output = V> + std::ops::Add<Output = V>>(values: &[V; 3], b1: f32, b2: f32) -> V
+{
+    values[0] * (1.0 - b1 - b2) + values[1] * b1 + values[2] * b2
+}
+
+// Orients the hit record using the geometric normal, then replaces it with the shading normal
+// flipped to the same side, so that smooth normals never disagree with `front_face`.
+pub(crate) fn set_shading_normal(hit_record: &mut HitRecord, ray: &Ray, shading_normal: Vec3)
+{
+    hit_record.set_normal(ray);
+    hit_record.normal = if hit_record.front_face { shading_normal } else { -shading_normal };
+}
+
+/// Single triangle with optional per-vertex normals and texture coordinates.
+pub struct Triangle<T: Material>
+{
+    vertices: [Vec3; 3],
+    normals: Option<[Vec3; 3]>,
+    uvs: Option<[(f32, f32); 3]>,
+    normal: Vec3,
+    area: f32,
+    material: T,
+    bbox: Aabb,
+}
+
+impl<T: Material> Triangle<T>
+{
+    pub fn new(a: Vec3, b: Vec3, c: Vec3, material: T) -> Triangle<T>
+    {
+        let n = (b - a).cross(c - a);
+        let bbox = Aabb::surrounding(&Aabb::new(a, b), &Aabb::new(c, c));
+        Triangle { vertices: [a, b, c], normals: None, uvs: None, normal: n.normalize(), area: 0.5 * n.length(), material, bbox }
+    }
+
+    pub fn with_normals(mut self, normals: [Vec3; 3]) -> Triangle<T>
+    {
+        self.normals = Some(normals.map(|n| n.normalize()));
+        self
+    }
+
+    pub fn with_uvs(mut self, uvs: [(f32, f32); 3]) -> Triangle<T>
+    {
+        self.uvs = Some(uvs);
+        self
+    }
+}
+
+impl<T: Material> Hittable for Triangle<T> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        let (t, b1, b2) = intersect_triangle(ray, &self.vertices, t_min, t_max)?;
+
+        let (u, v) = match self.uvs {
+            Some(uvs) => (interpolate(&uvs.map(|uv| uv.0), b1, b2), interpolate(&uvs.map(|uv| uv.1), b1, b2)),
+            None => (b1, b2),
+        };
+
+        let mut hit_record = HitRecord {
+            point: ray.at(t),
+            normal: self.normal,
+            t,
+            material: &self.material,
+            front_face: false,
+            u,
+            v,
+        };
+
+        let shading_normal = self.normals.map_or(self.normal, |normals| interpolate(&normals, b1, b2).normalize());
+        set_shading_normal(&mut hit_record, ray, shading_normal);
+
+        Some(hit_record)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    fn pdf(&self, origin: &Vec3, direction: &Vec3, time: f32) -> f32 {
+        match intersect_triangle(&Ray::new(*origin, *direction, time), &self.vertices, 0.001, f32::INFINITY) {
+            Some((t, _, _)) => {
+                let distance_squared = t * t * direction.length_squared();
+                let cosine = (direction.dot(self.normal) / direction.length()).abs();
+                distance_squared / (cosine * self.area)
+            }
+            None => 0.0,
+        }
+    }
+
+    fn sample(&self, origin: &Vec3, _time: f32) -> Vec3 {
+        let mut rng = rand::thread_rng();
+        let (r1, r2) = (rng.gen::<f32>().sqrt(), rng.gen::<f32>());
+        let point = interpolate(&self.vertices, r1 * (1.0 - r2), r1 * r2);
+        point - *origin
+    }
+}
@@ -2,7 +2,9 @@ use rand::Rng;
 use std::fmt::{Display, Formatter};
 use std::ops::*;
 
-#[derive(Clone, Copy)]
+/// Three component vector used for directions and colors, and for points wherever no transform
+/// needs to tell them apart; see [`Point3`].
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Vec3 {
     pub(crate) e: [f32; 3],
 }
@@ -186,3 +188,522 @@ impl Neg for Vec3 {
     }
 }
 
+/// Position in space. Transforms move points, while they only turn and stretch directions.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Point3(pub Vec3);
+
+impl Point3 {
+    pub fn new(x: f32, y: f32, z: f32) -> Self {
+        Point3(Vec3::new(x, y, z))
+    }
+}
+
+impl Sub for Point3 {
+    type Output = Vec3;
+    fn sub(self, rhs: Point3) -> Vec3 {
+        self.0 - rhs.0
+    }
+}
+
+impl Add<Vec3> for Point3 {
+    type Output = Point3;
+    fn add(self, rhs: Vec3) -> Point3 {
+        Point3(self.0 + rhs)
+    }
+}
+
+/// Unit surface normal. Transforms keep it perpendicular to the surface by using the inverse
+/// transpose of their linear part.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Normal3(Vec3);
+
+impl Normal3 {
+    /// Normal along `n`, scaled to unit length.
+    pub fn new(n: Vec3) -> Self {
+        Normal3(n.normalize())
+    }
+
+    pub fn vector(self) -> Vec3 {
+        self.0
+    }
+}
+
+/// Row-major 3x3 matrix for linear maps of directions.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Mat3 {
+    pub m: [[f32; 3]; 3],
+}
+
+impl Mat3 {
+    pub fn identity() -> Self {
+        Mat3::from_diagonal(Vec3::new(1.0, 1.0, 1.0))
+    }
+
+    pub fn from_diagonal(d: Vec3) -> Self {
+        let mut m = [[0.0; 3]; 3];
+        for (i, row) in m.iter_mut().enumerate() {
+            row[i] = d.e[i];
+        }
+        Mat3 { m }
+    }
+
+    pub fn from_columns(x: Vec3, y: Vec3, z: Vec3) -> Self {
+        Mat3 {
+            m: [
+                [x.e[0], y.e[0], z.e[0]],
+                [x.e[1], y.e[1], z.e[1]],
+                [x.e[2], y.e[2], z.e[2]],
+            ],
+        }
+    }
+
+    pub fn column(&self, j: usize) -> Vec3 {
+        Vec3::new(self.m[0][j], self.m[1][j], self.m[2][j])
+    }
+
+    /// Counterclockwise rotation by `degrees` about `axis`, looking down the axis towards the
+    /// origin (Rodrigues' formula).
+    pub fn rotation(axis: Vec3, degrees: f32) -> Self {
+        let a = axis.normalize();
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        let t = 1.0 - cos;
+        let (x, y, z) = (a.e[0], a.e[1], a.e[2]);
+        Mat3 {
+            m: [
+                [t * x * x + cos, t * x * y - sin * z, t * x * z + sin * y],
+                [t * x * y + sin * z, t * y * y + cos, t * y * z - sin * x],
+                [t * x * z - sin * y, t * y * z + sin * x, t * z * z + cos],
+            ],
+        }
+    }
+
+    pub fn transpose(&self) -> Mat3 {
+        Mat3::from_columns(
+            Vec3::new(self.m[0][0], self.m[0][1], self.m[0][2]),
+            Vec3::new(self.m[1][0], self.m[1][1], self.m[1][2]),
+            Vec3::new(self.m[2][0], self.m[2][1], self.m[2][2]),
+        )
+    }
+
+    pub fn determinant(&self) -> f32 {
+        let (x, y, z) = (self.column(0), self.column(1), self.column(2));
+        x.dot(y.cross(z))
+    }
+
+    /// Inverse from the cofactors. Returns `None` for singular matrices.
+    pub fn inverse(&self) -> Option<Mat3> {
+        let determinant = self.determinant();
+        if determinant.abs() < 1e-12 {
+            return None;
+        }
+
+        // The rows of the inverse are the cross products of pairs of columns.
+        let (x, y, z) = (self.column(0), self.column(1), self.column(2));
+        let rows = [y.cross(z), z.cross(x), x.cross(y)].map(|r| r / determinant);
+        Some(Mat3 {
+            m: rows.map(|r| r.e),
+        })
+    }
+}
+
+impl Mul<Mat3> for Mat3 {
+    type Output = Mat3;
+    fn mul(self, rhs: Mat3) -> Mat3 {
+        let mut m = [[0.0; 3]; 3];
+        for (i, row) in m.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = (0..3).map(|k| self.m[i][k] * rhs.m[k][j]).sum();
+            }
+        }
+        Mat3 { m }
+    }
+}
+
+impl Mul<Vec3> for Mat3 {
+    type Output = Vec3;
+    fn mul(self, v: Vec3) -> Vec3 {
+        Vec3::new(
+            self.m[0][0] * v.e[0] + self.m[0][1] * v.e[1] + self.m[0][2] * v.e[2],
+            self.m[1][0] * v.e[0] + self.m[1][1] * v.e[1] + self.m[1][2] * v.e[2],
+            self.m[2][0] * v.e[0] + self.m[2][1] * v.e[1] + self.m[2][2] * v.e[2],
+        )
+    }
+}
+
+/// Row-major 4x4 matrix for affine transforms of points and directions.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Mat4 {
+    pub m: [[f32; 4]; 4],
+}
+
+impl Mat4 {
+    pub fn identity() -> Self {
+        Mat4::from_linear(Mat3::identity(), Vec3::new_zero())
+    }
+
+    /// Affine transform applying `linear` and then moving by `translation`.
+    pub fn from_linear(linear: Mat3, translation: Vec3) -> Self {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().take(3).enumerate() {
+            row[..3].copy_from_slice(&linear.m[i]);
+            row[3] = translation.e[i];
+        }
+        m[3][3] = 1.0;
+        Mat4 { m }
+    }
+
+    pub fn translation(offset: Vec3) -> Self {
+        Mat4::from_linear(Mat3::identity(), offset)
+    }
+
+    pub fn scaling(factors: Vec3) -> Self {
+        Mat4::from_linear(Mat3::from_diagonal(factors), Vec3::new_zero())
+    }
+
+    /// Counterclockwise rotation by `degrees` about an axis through the origin.
+    pub fn rotation(axis: Vec3, degrees: f32) -> Self {
+        Mat4::from_linear(Mat3::rotation(axis, degrees), Vec3::new_zero())
+    }
+
+    /// Counterclockwise rotation about the x axis, looking down the axis towards the origin.
+    pub fn rotation_x(degrees: f32) -> Self {
+        Mat4::rotation(Vec3::new(1.0, 0.0, 0.0), degrees)
+    }
+
+    /// Counterclockwise rotation about the y axis, looking down the axis towards the origin.
+    pub fn rotation_y(degrees: f32) -> Self {
+        Mat4::rotation(Vec3::new(0.0, 1.0, 0.0), degrees)
+    }
+
+    /// Counterclockwise rotation about the z axis, looking down the axis towards the origin.
+    pub fn rotation_z(degrees: f32) -> Self {
+        Mat4::rotation(Vec3::new(0.0, 0.0, 1.0), degrees)
+    }
+
+    /// Maps a frame at the origin looking down -z with +y up to one at `from` looking towards
+    /// `at`, with its y axis as close to `up` as possible. Its columns are the camera's right,
+    /// up and backward directions and its position.
+    pub fn look_at(from: Point3, at: Point3, up: Vec3) -> Self {
+        let w = (from - at).normalize();
+        let u = up.cross(w).normalize();
+        let v = w.cross(u);
+        Mat4::from_linear(Mat3::from_columns(u, v, w), from.0)
+    }
+
+    /// Upper left 3x3 part, which transforms directions.
+    pub fn linear(&self) -> Mat3 {
+        let mut m = [[0.0; 3]; 3];
+        for (i, row) in m.iter_mut().enumerate() {
+            row.copy_from_slice(&self.m[i][..3]);
+        }
+        Mat3 { m }
+    }
+
+    pub fn translation_part(&self) -> Vec3 {
+        Vec3::new(self.m[0][3], self.m[1][3], self.m[2][3])
+    }
+
+    /// Matrix that transforms normals consistently with `self`: the inverse transpose of the
+    /// linear part. Returns `None` if `self` is singular.
+    pub fn normal_matrix(&self) -> Option<Mat3> {
+        Some(self.linear().inverse()?.transpose())
+    }
+
+    /// Inverse of an affine transform, one whose bottom row is `0 0 0 1`. Only the linear part
+    /// and the translation are read, so projective matrices give wrong results. Returns `None`
+    /// for singular matrices.
+    pub fn inverse(&self) -> Option<Mat4> {
+        debug_assert_eq!(
+            self.m[3],
+            [0.0, 0.0, 0.0, 1.0],
+            "Mat4::inverse needs an affine matrix"
+        );
+        let linear = self.linear().inverse()?;
+        Some(Mat4::from_linear(
+            linear,
+            -(linear * self.translation_part()),
+        ))
+    }
+
+    pub fn transpose(&self) -> Mat4 {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = self.m[j][i];
+            }
+        }
+        Mat4 { m }
+    }
+
+    pub fn transform_point(&self, p: Point3) -> Point3 {
+        Point3(self.linear() * p.0 + self.translation_part())
+    }
+
+    /// Transforms a direction, ignoring the translation.
+    pub fn transform_vector(&self, v: Vec3) -> Vec3 {
+        self.linear() * v
+    }
+
+    /// Transforms a normal by the inverse transpose and renormalizes it, or returns `None` if
+    /// `self` is singular. Computes the inverse on every call, so hot paths should keep a
+    /// [`normal_matrix`](Mat4::normal_matrix) instead.
+    pub fn transform_normal(&self, n: Normal3) -> Option<Normal3> {
+        Some(Normal3::new(self.normal_matrix()? * n.0))
+    }
+}
+
+impl Mul<Mat4> for Mat4 {
+    type Output = Mat4;
+    fn mul(self, rhs: Mat4) -> Mat4 {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = (0..4).map(|k| self.m[i][k] * rhs.m[k][j]).sum();
+            }
+        }
+        Mat4 { m }
+    }
+}
+
+/// Unit quaternion `w + x·i + y·j + z·k` representing a rotation, which unlike a matrix can be
+/// interpolated without distorting the shape.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Quat {
+    pub w: f32,
+    pub v: Vec3,
+}
+
+impl Quat {
+    pub fn identity() -> Self {
+        Quat {
+            w: 1.0,
+            v: Vec3::new_zero(),
+        }
+    }
+
+    /// Counterclockwise rotation by `degrees` about `axis`, matching [`Mat3::rotation`].
+    pub fn from_axis_angle(axis: Vec3, degrees: f32) -> Self {
+        let (sin, cos) = (0.5 * degrees.to_radians()).sin_cos();
+        Quat {
+            w: cos,
+            v: sin * axis.normalize(),
+        }
+    }
+
+    /// Rotation given by a matrix with orthonormal columns and a positive determinant
+    /// (Shepperd's method, which divides by the largest component for stability).
+    pub fn from_mat3(r: &Mat3) -> Self {
+        let m = &r.m;
+        let trace = m[0][0] + m[1][1] + m[2][2];
+        let (w, x, y, z) = if trace > 0.0 {
+            let s = 2.0 * (trace + 1.0).sqrt();
+            (
+                0.25 * s,
+                (m[2][1] - m[1][2]) / s,
+                (m[0][2] - m[2][0]) / s,
+                (m[1][0] - m[0][1]) / s,
+            )
+        } else if m[0][0] > m[1][1] && m[0][0] > m[2][2] {
+            let s = 2.0 * (1.0 + m[0][0] - m[1][1] - m[2][2]).sqrt();
+            (
+                (m[2][1] - m[1][2]) / s,
+                0.25 * s,
+                (m[0][1] + m[1][0]) / s,
+                (m[0][2] + m[2][0]) / s,
+            )
+        } else if m[1][1] > m[2][2] {
+            let s = 2.0 * (1.0 + m[1][1] - m[0][0] - m[2][2]).sqrt();
+            (
+                (m[0][2] - m[2][0]) / s,
+                (m[0][1] + m[1][0]) / s,
+                0.25 * s,
+                (m[1][2] + m[2][1]) / s,
+            )
+        } else {
+            let s = 2.0 * (1.0 + m[2][2] - m[0][0] - m[1][1]).sqrt();
+            (
+                (m[1][0] - m[0][1]) / s,
+                (m[0][2] + m[2][0]) / s,
+                (m[1][2] + m[2][1]) / s,
+                0.25 * s,
+            )
+        };
+        Quat {
+            w,
+            v: Vec3::new(x, y, z),
+        }
+        .normalize()
+    }
+
+    pub fn to_mat3(&self) -> Mat3 {
+        let (w, x, y, z) = (self.w, self.v.e[0], self.v.e[1], self.v.e[2]);
+        Mat3 {
+            m: [
+                [
+                    1.0 - 2.0 * (y * y + z * z),
+                    2.0 * (x * y - w * z),
+                    2.0 * (x * z + w * y),
+                ],
+                [
+                    2.0 * (x * y + w * z),
+                    1.0 - 2.0 * (x * x + z * z),
+                    2.0 * (y * z - w * x),
+                ],
+                [
+                    2.0 * (x * z - w * y),
+                    2.0 * (y * z + w * x),
+                    1.0 - 2.0 * (x * x + y * y),
+                ],
+            ],
+        }
+    }
+
+    pub fn dot(&self, other: Quat) -> f32 {
+        self.w * other.w + self.v.dot(other.v)
+    }
+
+    pub fn normalize(self) -> Self {
+        let length = self.dot(self).sqrt();
+        Quat {
+            w: self.w / length,
+            v: self.v / length,
+        }
+    }
+
+    /// Spherical linear interpolation from `self` at `t = 0` to `other` at `t = 1`, turning at
+    /// a constant rate along the shorter way round.
+    pub fn slerp(self, other: Quat, t: f32) -> Quat {
+        // q and -q are the same rotation; pick the one on the near side.
+        let mut cos = self.dot(other);
+        let other = if cos < 0.0 {
+            cos = -cos;
+            Quat {
+                w: -other.w,
+                v: -other.v,
+            }
+        } else {
+            other
+        };
+
+        // Nearly identical rotations would divide by almost zero, and a straight line is as good.
+        let (a, b) = if cos > 0.9995 {
+            (1.0 - t, t)
+        } else {
+            let theta = cos.acos();
+            let sin = theta.sin();
+            (((1.0 - t) * theta).sin() / sin, (t * theta).sin() / sin)
+        };
+        Quat {
+            w: a * self.w + b * other.w,
+            v: a * self.v + b * other.v,
+        }
+        .normalize()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: Vec3, b: Vec3) {
+        assert!((a - b).length() < 1e-5, "{a:?} != {b:?}");
+    }
+
+    #[test]
+    fn points_move_with_the_translation_but_vectors_do_not() {
+        let transform = Mat4::translation(Vec3::new(1.0, 2.0, 3.0)) * Mat4::rotation_z(90.0);
+        let v = Vec3::new(1.0, 0.0, 0.0);
+        assert_close(
+            transform.transform_point(Point3(v)).0,
+            Vec3::new(1.0, 3.0, 3.0),
+        );
+        assert_close(transform.transform_vector(v), Vec3::new(0.0, 1.0, 0.0));
+        assert_close(
+            Mat4::look_at(Point3::new(0.0, 0.0, 5.0), Point3::new(0.0, 0.0, 0.0), v)
+                .translation_part(),
+            Vec3::new(0.0, 0.0, 5.0),
+        );
+    }
+
+    #[test]
+    fn inverse_undoes_an_affine_transform() {
+        let transform = Mat4::translation(Vec3::new(1.0, -2.0, 3.0))
+            * Mat4::rotation(Vec3::new(1.0, 1.0, 0.0), 40.0)
+            * Mat4::scaling(Vec3::new(2.0, 0.5, -1.5));
+        let inverse = transform.inverse().unwrap();
+
+        let p = Point3::new(0.3, 0.7, -1.1);
+        assert_close(inverse.transform_point(transform.transform_point(p)).0, p.0);
+        assert_close(
+            transform.transform_vector(inverse.transform_vector(p.0)),
+            p.0,
+        );
+        assert!(Mat4::scaling(Vec3::new(1.0, 0.0, 1.0)).inverse().is_none());
+    }
+
+    #[test]
+    fn normals_stay_perpendicular_to_transformed_surfaces() {
+        let transform = Mat4::rotation_z(30.0) * Mat4::scaling(Vec3::new(3.0, 1.0, 1.0));
+        let (tangent, normal) = (
+            Vec3::new(1.0, -1.0, 0.0),
+            Normal3::new(Vec3::new(1.0, 1.0, 0.0)),
+        );
+
+        let normal = transform.transform_normal(normal).unwrap();
+        assert!(
+            normal
+                .vector()
+                .dot(transform.transform_vector(tangent))
+                .abs()
+                < 1e-5
+        );
+        assert!((normal.vector().length() - 1.0).abs() < 1e-5);
+        assert!(Mat4::scaling(Vec3::new_zero())
+            .transform_normal(normal)
+            .is_none());
+    }
+
+    fn assert_same_rotation(a: Quat, b: Quat) {
+        // q and -q are the same rotation.
+        assert!(a.dot(b).abs() > 1.0 - 1e-5, "{a:?} != {b:?}");
+    }
+
+    #[test]
+    fn quaternions_round_trip_through_every_branch_of_from_mat3() {
+        // A small angle has a positive trace; half turns about each axis make that axis'
+        // diagonal entry the largest.
+        let rotations = [
+            (Vec3::new(1.0, 2.0, 3.0), 40.0),
+            (Vec3::new(1.0, 0.3, -0.2), 160.0),
+            (Vec3::new(0.2, 1.0, 0.3), 160.0),
+            (Vec3::new(0.3, -0.2, 1.0), 160.0),
+        ];
+        for (axis, degrees) in rotations {
+            let q = Quat::from_axis_angle(axis, degrees);
+            let matrix = Mat3::rotation(axis, degrees);
+            for (row, expected) in q.to_mat3().m.iter().zip(&matrix.m) {
+                assert_close(Vec3 { e: *row }, Vec3 { e: *expected });
+            }
+            assert_same_rotation(Quat::from_mat3(&matrix), q);
+        }
+    }
+
+    #[test]
+    fn slerp_turns_along_the_shorter_arc() {
+        let z = Vec3::new(0.0, 0.0, 1.0);
+        let (start, end) = (Quat::identity(), Quat::from_axis_angle(z, 90.0));
+
+        assert_same_rotation(start.slerp(end, 0.0), start);
+        assert_same_rotation(start.slerp(end, 1.0), end);
+        assert_same_rotation(start.slerp(end, 0.5), Quat::from_axis_angle(z, 45.0));
+        assert_same_rotation(end.slerp(start, 0.25), Quat::from_axis_angle(z, 67.5));
+
+        // A turn of 300 degrees is reached by turning back 60.
+        let back = Quat::from_axis_angle(z, 300.0);
+        assert_same_rotation(start.slerp(back, 0.5), Quat::from_axis_angle(z, -30.0));
+
+        // Close rotations take the linear branch.
+        let near = Quat::from_axis_angle(z, 1.0);
+        assert_same_rotation(start.slerp(near, 0.5), Quat::from_axis_angle(z, 0.5));
+    }
+}